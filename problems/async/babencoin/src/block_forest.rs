@@ -70,10 +70,30 @@ impl BlockForest {
         &self.pending_transactions
     }
 
+    /// Balance of the wallet after the head block.
+    pub fn balance(&self, wallet_id: &WalletId) -> u64 {
+        self.balance_snapshots[self.head.hash()]
+            .get(wallet_id)
+            .copied()
+            .unwrap_or(0)
+    }
+
     pub fn find_block(&self, hash: &BlockHash) -> Option<&Arc<VerifiedBlock>> {
         self.blocks.get(hash)
     }
 
+    /// Find the block with the given index in the chain of the head block.
+    pub fn find_block_by_index(&self, index: u64) -> Option<&Arc<VerifiedBlock>> {
+        let mut block = &self.head;
+        if index > block.index {
+            return None;
+        }
+        while block.index > index {
+            block = &self.blocks[&block.prev_hash];
+        }
+        Some(block)
+    }
+
     pub fn next_max_hash(&self) -> BlockHash {
         let next_index = self.head.index + 1;
         if next_index % EPOCH_SIZE as u64 > 0 {
@@ -0,0 +1,363 @@
+use crate::{
+    block_forest::BlockForest,
+    data::{Block, BlockHash, Transaction, VerifiedBlock, VerifiedTransaction, HASH_LEN},
+};
+
+use anyhow::{bail, Context, Result};
+use byteorder::{BigEndian, ByteOrder, WriteBytesExt};
+use log::*;
+use serde::{Deserialize, Serialize};
+
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::{ErrorKind, Read, Seek, SeekFrom, Write},
+    path::PathBuf,
+};
+
+////////////////////////////////////////////////////////////////////////////////
+
+const BLOCKS_FILE_NAME: &str = "blocks.log";
+const INDEX_FILE_NAME: &str = "blocks.idx";
+const PENDING_FILE_NAME: &str = "pending.json";
+
+// Every record of the blocks file is the length of the block followed by its JSON.
+const RECORD_HEADER_LEN: u64 = 4;
+// Every entry of the index is the block hash, the offset of its record and the block length.
+const INDEX_ENTRY_LEN: usize = HASH_LEN + 8 + 4;
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct StorageConfig {
+    /// Directory with the stored blocks. If not set, the node keeps everything in memory.
+    pub path: Option<PathBuf>,
+    /// Sync the files to disk after every write.
+    #[serde(default)]
+    pub sync_writes: bool,
+}
+
+#[derive(Clone, Copy)]
+struct IndexEntry {
+    offset: u64,
+    len: u32,
+}
+
+impl IndexEntry {
+    fn end(&self) -> u64 {
+        self.offset + RECORD_HEADER_LEN + self.len as u64
+    }
+}
+
+/// Append-only storage of the blocks known to the node and a snapshot of its pending
+/// transactions, so that the node is able to restart without downloading the chain again.
+pub struct BlockStore {
+    path: PathBuf,
+    sync_writes: bool,
+    blocks_file: File,
+    index_file: File,
+    index: HashMap<BlockHash, IndexEntry>,
+    order: Vec<BlockHash>,
+    blocks_len: u64,
+}
+
+impl BlockStore {
+    pub fn open(config: &StorageConfig) -> Result<Option<Self>> {
+        let path = match &config.path {
+            Some(path) => path,
+            None => return Ok(None),
+        };
+        fs::create_dir_all(path)
+            .with_context(|| format!("failed to create storage directory {}", path.display()))?;
+
+        let blocks_path = path.join(BLOCKS_FILE_NAME);
+        let blocks_file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&blocks_path)
+            .with_context(|| format!("failed to open {}", blocks_path.display()))?;
+
+        let index_path = path.join(INDEX_FILE_NAME);
+        let index_file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&index_path)
+            .with_context(|| format!("failed to open {}", index_path.display()))?;
+
+        let mut store = Self {
+            path: path.clone(),
+            sync_writes: config.sync_writes,
+            blocks_file,
+            index_file,
+            index: HashMap::new(),
+            order: vec![],
+            blocks_len: 0,
+        };
+        store
+            .recover()
+            .with_context(|| format!("failed to recover storage {}", path.display()))?;
+        Ok(Some(store))
+    }
+
+    /// Rebuilds the forest from the stored blocks, every block is validated again.
+    pub fn load_forest(&mut self) -> Result<BlockForest> {
+        let mut block_forest = BlockForest::new();
+        for hash in self.order.clone() {
+            let block = self.read_block(&hash)?;
+            if let Err(err) = block_forest.add_block(block) {
+                debug!(
+                    "stored block {} is rejected: {:#}",
+                    base64::encode(hash),
+                    err
+                );
+            }
+        }
+
+        let mut pending = self.load_pending()?;
+        // A transaction may depend on another one, so retry while some of them are added.
+        loop {
+            let count = pending.len();
+            pending.retain(|tx| block_forest.add_transaction(tx.clone()).is_err());
+            if pending.is_empty() || pending.len() == count {
+                break;
+            }
+        }
+        for tx in pending {
+            debug!(
+                "stored pending transaction {} is discarded",
+                base64::encode(tx.hash())
+            );
+        }
+
+        info!(
+            "restored {} blocks and {} pending transactions from {}, head index: {}",
+            self.order.len(),
+            block_forest.pending_transactions().len(),
+            self.path.display(),
+            block_forest.head().index,
+        );
+        Ok(block_forest)
+    }
+
+    pub fn contains(&self, hash: &BlockHash) -> bool {
+        self.index.contains_key(hash)
+    }
+
+    pub fn append_block(&mut self, block: &VerifiedBlock) -> Result<()> {
+        if self.contains(block.hash()) || block.index == 0 {
+            return Ok(());
+        }
+
+        let data = serde_json::to_vec(&block.to_block()).context("failed to serialize block")?;
+        let entry = IndexEntry {
+            offset: self.blocks_len,
+            len: data.len().try_into().context("block is too large")?,
+        };
+
+        let mut record = Vec::with_capacity(RECORD_HEADER_LEN as usize + data.len());
+        record.write_u32::<BigEndian>(entry.len)?;
+        record.extend_from_slice(&data);
+        self.blocks_file
+            .write_all(&record)
+            .context("failed to write block")?;
+
+        // The index is written after the block, so it never points to a missing record.
+        self.index_file
+            .write_all(&encode_index_entry(block.hash(), &entry))
+            .context("failed to write index entry")?;
+
+        if self.sync_writes {
+            self.blocks_file.sync_data()?;
+            self.index_file.sync_data()?;
+        }
+
+        self.blocks_len = entry.end();
+        self.index.insert(*block.hash(), entry);
+        self.order.push(*block.hash());
+        Ok(())
+    }
+
+    pub fn read_block(&mut self, hash: &BlockHash) -> Result<VerifiedBlock> {
+        let entry = match self.index.get(hash) {
+            Some(entry) => *entry,
+            None => bail!("block {} is not stored", base64::encode(hash)),
+        };
+
+        let mut data = vec![0u8; entry.len as usize];
+        self.blocks_file
+            .seek(SeekFrom::Start(entry.offset + RECORD_HEADER_LEN))?;
+        self.blocks_file
+            .read_exact(&mut data)
+            .context("failed to read block")?;
+
+        let block: Block = serde_json::from_slice(&data)
+            .with_context(|| format!("block at offset {} is corrupted", entry.offset))?;
+        let block = block
+            .verified()
+            .with_context(|| format!("block at offset {} is invalid", entry.offset))?;
+        if block.hash() != hash {
+            bail!(
+                "block at offset {} doesn't match its index entry",
+                entry.offset
+            );
+        }
+        Ok(block)
+    }
+
+    /// Replaces the stored pending transactions with the given ones.
+    pub fn save_pending<'a>(
+        &self,
+        transactions: impl Iterator<Item = &'a VerifiedTransaction>,
+    ) -> Result<()> {
+        let transactions = transactions
+            .cloned()
+            .map(Transaction::from)
+            .collect::<Vec<_>>();
+        let data = serde_json::to_vec(&transactions)
+            .context("failed to serialize pending transactions")?;
+
+        // Write a temporary file and rename it, so the snapshot is never half-written.
+        let path = self.path.join(PENDING_FILE_NAME);
+        let tmp_path = path.with_extension("tmp");
+        let mut file = File::create(&tmp_path)
+            .with_context(|| format!("failed to create {}", tmp_path.display()))?;
+        file.write_all(&data)?;
+        if self.sync_writes {
+            file.sync_data()?;
+        }
+        fs::rename(&tmp_path, &path)
+            .with_context(|| format!("failed to replace {}", path.display()))
+    }
+
+    fn load_pending(&self) -> Result<Vec<VerifiedTransaction>> {
+        let path = self.path.join(PENDING_FILE_NAME);
+        let data = match fs::read(&path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(vec![]),
+            Err(err) => {
+                return Err(err).with_context(|| format!("failed to read {}", path.display()))
+            }
+        };
+
+        let transactions: Vec<Transaction> = serde_json::from_slice(&data)
+            .with_context(|| format!("failed to parse {}", path.display()))?;
+        Ok(transactions
+            .into_iter()
+            .filter_map(|tx| match tx.verified() {
+                Ok(tx) => Some(tx),
+                Err(err) => {
+                    warn!("stored pending transaction is invalid: {:#}", err);
+                    None
+                }
+            })
+            .collect())
+    }
+
+    /// Loads the index and brings it in line with the blocks file. The node may have been
+    /// killed in the middle of a write, so a torn record at the end of either file is dropped
+    /// and the blocks missing from the index are indexed again.
+    fn recover(&mut self) -> Result<()> {
+        let blocks_file_len = self.blocks_file.metadata()?.len();
+
+        let mut index_data = vec![];
+        self.index_file.seek(SeekFrom::Start(0))?;
+        self.index_file.read_to_end(&mut index_data)?;
+
+        let mut index_is_valid = index_data.len() % INDEX_ENTRY_LEN == 0;
+        for chunk in index_data.chunks_exact(INDEX_ENTRY_LEN) {
+            let (hash, entry) = decode_index_entry(chunk);
+            if entry.offset < self.blocks_len
+                || entry.end() > blocks_file_len
+                || self.index.contains_key(&hash)
+            {
+                index_is_valid = false;
+                break;
+            }
+            self.blocks_len = entry.end();
+            self.index.insert(hash, entry);
+            self.order.push(hash);
+        }
+
+        let indexed_count = self.order.len();
+        self.scan_blocks(blocks_file_len)?;
+        if self.order.len() > indexed_count {
+            warn!(
+                "{} stored blocks were missing from the index",
+                self.order.len() - indexed_count
+            );
+            index_is_valid = false;
+        }
+
+        if self.blocks_len < blocks_file_len {
+            warn!(
+                "dropping {} bytes of a torn block record",
+                blocks_file_len - self.blocks_len
+            );
+            self.blocks_file.set_len(self.blocks_len)?;
+        }
+
+        if !index_is_valid {
+            let mut data = Vec::with_capacity(self.order.len() * INDEX_ENTRY_LEN);
+            for hash in self.order.iter() {
+                data.extend_from_slice(&encode_index_entry(hash, &self.index[hash]));
+            }
+            self.index_file.set_len(0)?;
+            self.index_file.write_all(&data)?;
+            self.index_file.sync_data()?;
+        }
+
+        Ok(())
+    }
+
+    // Indexes the complete records following the last indexed one.
+    fn scan_blocks(&mut self, blocks_file_len: u64) -> Result<()> {
+        let mut header = [0u8; RECORD_HEADER_LEN as usize];
+        while self.blocks_len + RECORD_HEADER_LEN <= blocks_file_len {
+            self.blocks_file.seek(SeekFrom::Start(self.blocks_len))?;
+            self.blocks_file.read_exact(&mut header)?;
+            let entry = IndexEntry {
+                offset: self.blocks_len,
+                len: BigEndian::read_u32(&header),
+            };
+            if entry.end() > blocks_file_len {
+                break;
+            }
+
+            let mut data = vec![0u8; entry.len as usize];
+            self.blocks_file.read_exact(&mut data)?;
+            let hash = match serde_json::from_slice::<Block>(&data) {
+                Ok(block) => block.compute_hash(),
+                Err(_) => break,
+            };
+
+            self.blocks_len = entry.end();
+            if !self.index.contains_key(&hash) {
+                self.index.insert(hash, entry);
+                self.order.push(hash);
+            }
+        }
+        Ok(())
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+fn encode_index_entry(hash: &BlockHash, entry: &IndexEntry) -> [u8; INDEX_ENTRY_LEN] {
+    let mut data = [0u8; INDEX_ENTRY_LEN];
+    data[..HASH_LEN].copy_from_slice(hash);
+    BigEndian::write_u64(&mut data[HASH_LEN..HASH_LEN + 8], entry.offset);
+    BigEndian::write_u32(&mut data[HASH_LEN + 8..], entry.len);
+    data
+}
+
+fn decode_index_entry(data: &[u8]) -> (BlockHash, IndexEntry) {
+    let mut hash = [0u8; HASH_LEN];
+    hash.copy_from_slice(&data[..HASH_LEN]);
+    let entry = IndexEntry {
+        offset: BigEndian::read_u64(&data[HASH_LEN..HASH_LEN + 8]),
+        len: BigEndian::read_u32(&data[HASH_LEN + 8..]),
+    };
+    (hash, entry)
+}
@@ -1,6 +1,8 @@
 #![forbid(unsafe_code)]
 
 pub mod block_forest;
+pub mod block_store;
 pub mod data;
 pub mod node;
 pub mod util;
+pub mod wallet;
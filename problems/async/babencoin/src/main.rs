@@ -1,12 +1,17 @@
 #![forbid(unsafe_code)]
 
-use babencoin::node::{run_forever, Config};
+use babencoin::{
+    data::{Transaction, VerifiedTransaction, WalletId},
+    node::{run_forever, Config},
+    util::{encode_wallet_id, parse_pkcs8_public},
+    wallet,
+};
 
 use anyhow::{Context, Result};
 use log::*;
 use structopt::StructOpt;
 
-use std::{fs::File, io::Read};
+use std::{fs::File, io::Read, path::PathBuf};
 
 const DEFAULT_LOG_VERBOSITY: usize = 3;
 
@@ -15,7 +20,87 @@ const DEFAULT_LOG_VERBOSITY: usize = 3;
 struct Opts {
     /// Config path
     #[structopt(short = "c", long = "config")]
-    config_path: String,
+    config_path: Option<String>,
+
+    #[structopt(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(StructOpt, Debug)]
+enum Command {
+    /// Manage keys and transfer babencoins
+    Wallet(WalletCommand),
+}
+
+#[derive(StructOpt, Debug)]
+enum WalletCommand {
+    /// Generate a new key and print its wallet id
+    Generate {
+        /// Where to write the private key
+        #[structopt(short = "o", long = "output")]
+        key_path: PathBuf,
+        /// Size of the key in bits
+        #[structopt(long = "bits", default_value = "2048")]
+        bits: usize,
+    },
+    /// Print the wallet id of a key
+    Id {
+        /// Private key of the wallet
+        #[structopt(short = "k", long = "key")]
+        key_path: PathBuf,
+    },
+    /// Print the balance of a wallet at the head of a node
+    Balance {
+        /// Private key of the wallet
+        #[structopt(
+            short = "k",
+            long = "key",
+            required_unless = "wallet-id",
+            conflicts_with = "wallet-id"
+        )]
+        key_path: Option<PathBuf>,
+        /// Wallet id
+        #[structopt(long = "wallet-id")]
+        wallet_id: Option<String>,
+        /// Address of the node
+        #[structopt(short = "n", long = "node")]
+        node_address: String,
+    },
+    /// Sign a transaction and print it
+    Sign(TransferOpts),
+    /// Sign a transaction and submit it to a node
+    Send {
+        #[structopt(flatten)]
+        transfer: TransferOpts,
+        /// Address of the node
+        #[structopt(short = "n", long = "node")]
+        node_address: String,
+    },
+}
+
+#[derive(StructOpt, Debug)]
+struct TransferOpts {
+    /// Private key of the sender
+    #[structopt(short = "k", long = "key")]
+    key_path: PathBuf,
+    /// Wallet id of the receiver
+    #[structopt(long = "to")]
+    receiver: String,
+    #[structopt(long = "amount")]
+    amount: u64,
+    #[structopt(long = "fee", default_value = "0")]
+    fee: u64,
+    #[structopt(long = "comment", default_value = "")]
+    comment: String,
+}
+
+impl TransferOpts {
+    fn sign(self) -> Result<VerifiedTransaction> {
+        let key = wallet::read_key(&self.key_path)?;
+        let receiver = parse_pkcs8_public(&self.receiver).context("invalid receiver wallet id")?;
+        VerifiedTransaction::sign(&key, receiver.into(), self.amount, self.fee, self.comment)
+            .context("failed to sign transaction")
+    }
 }
 
 fn read_config(path: &str) -> Result<Config> {
@@ -43,6 +128,48 @@ fn get_verbosity() -> usize {
     }
 }
 
+fn run_wallet(command: WalletCommand) -> Result<()> {
+    match command {
+        WalletCommand::Generate { key_path, bits } => {
+            let key = wallet::generate_key(bits)?;
+            wallet::write_key(&key_path, &key)?;
+            println!("{}", encode_wallet_id(&key.to_public_key().into())?);
+        }
+        WalletCommand::Id { key_path } => {
+            let key = wallet::read_key(&key_path)?;
+            println!("{}", encode_wallet_id(&key.to_public_key().into())?);
+        }
+        WalletCommand::Balance {
+            key_path,
+            wallet_id,
+            node_address,
+        } => {
+            let wallet_id: WalletId = match (key_path, wallet_id) {
+                (Some(key_path), _) => wallet::read_key(&key_path)?.to_public_key().into(),
+                (None, Some(wallet_id)) => parse_pkcs8_public(&wallet_id)
+                    .context("invalid wallet id")?
+                    .into(),
+                (None, None) => unreachable!(),
+            };
+            let block_forest = wallet::fetch_chain(&node_address)?;
+            println!("{}", block_forest.balance(&wallet_id));
+        }
+        WalletCommand::Sign(transfer) => {
+            let tx = transfer.sign()?;
+            println!("{}", serde_json::to_string_pretty(&Transaction::from(tx))?);
+        }
+        WalletCommand::Send {
+            transfer,
+            node_address,
+        } => {
+            let tx = transfer.sign()?;
+            wallet::submit_transaction(&node_address, &tx)?;
+            println!("{}", base64::encode(tx.hash()));
+        }
+    }
+    Ok(())
+}
+
 fn do_main() -> Result<()> {
     let opts = Opts::from_args();
 
@@ -52,8 +179,16 @@ fn do_main() -> Result<()> {
         .init()
         .expect("failed to initialize logging");
 
-    let config = read_config(&opts.config_path)?;
-    run_forever(config)
+    match opts.command {
+        Some(Command::Wallet(command)) => run_wallet(command),
+        None => {
+            let config_path = opts
+                .config_path
+                .context("either a config or a subcommand is required")?;
+            let config = read_config(&config_path)?;
+            run_forever(config)
+        }
+    }
 }
 
 fn main() {
@@ -1,11 +1,17 @@
+mod api_service;
 mod gossip_service;
 mod mining_service;
 mod peer_service;
 
+use api_service::ApiService;
 use gossip_service::{GossipService, GossipServiceConfig};
 use mining_service::{MiningService, MiningServiceConfig};
 use peer_service::{PeerService, PeerServiceConfig};
 
+pub use api_service::ApiServiceConfig;
+
+use crate::block_store::{BlockStore, StorageConfig};
+
 use anyhow::{Context, Result};
 use crossbeam::channel;
 use serde::{Deserialize, Serialize};
@@ -19,6 +25,10 @@ pub struct Config {
     pub peer_service: PeerServiceConfig,
     pub gossip_service: GossipServiceConfig,
     pub mining_service: MiningServiceConfig,
+    #[serde(default)]
+    pub storage: StorageConfig,
+    #[serde(default)]
+    pub api_service: Option<ApiServiceConfig>,
 }
 
 pub fn run_forever(config: Config) -> Result<()> {
@@ -31,13 +41,30 @@ pub fn run_forever(config: Config) -> Result<()> {
         PeerService::new(config.peer_service, peer_event_sender, command_receiver)
             .context("failed to create peer service")?;
 
+    let (api_request_sender, api_request_receiver) = channel::bounded(1000);
+    let api_service = config
+        .api_service
+        .map(|api_config| ApiService::new(api_config, api_request_sender))
+        .transpose()
+        .context("failed to create api service")?;
+    // The gossip service would spin on a disconnected channel.
+    let api_request_receiver = match api_service {
+        Some(_) => api_request_receiver,
+        None => channel::never(),
+    };
+
+    let block_store = BlockStore::open(&config.storage).context("failed to open block storage")?;
+
     let mut gossip_service = GossipService::new(
         config.gossip_service,
         peer_event_receiver,
         command_sender,
         block_receiver,
         mining_info_sender,
-    );
+        api_request_receiver,
+        block_store,
+    )
+    .context("failed to create gossip service")?;
 
     let mut mining_service =
         MiningService::new(config.mining_service, mining_info_receiver, block_sender);
@@ -52,6 +79,13 @@ pub fn run_forever(config: Config) -> Result<()> {
         panic!("mining service terminated");
     });
 
+    if let Some(mut api_service) = api_service {
+        thread::spawn(move || {
+            api_service.run();
+            panic!("api service terminated");
+        });
+    }
+
     peer_service.run();
     panic!("peer service terminated");
 }
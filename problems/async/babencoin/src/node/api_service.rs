@@ -0,0 +1,372 @@
+#![forbid(unsafe_code)]
+
+use crate::{
+    data::{
+        Block, BlockHash, Transaction, TransactionHash, VerifiedBlock, VerifiedTransaction,
+        WalletId, HASH_LEN,
+    },
+    node::peer_service::SessionId,
+    util::{deserialize_base64_fixed, deserialize_wallet_id, serialize_base64},
+};
+
+use anyhow::{bail, Context, Result};
+use crossbeam::channel::{bounded, Sender, TrySendError};
+use log::*;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    thread,
+    time::Duration,
+};
+
+////////////////////////////////////////////////////////////////////////////////
+
+const MAX_BODY_LEN: usize = 65536;
+const WORKER_COUNT: usize = 4;
+const MAX_QUEUED_CONNECTIONS: usize = 64;
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(10);
+
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+const TRANSACTION_REJECTED: i64 = -32000;
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct ApiServiceConfig {
+    pub listen_address: String,
+}
+
+#[derive(Debug)]
+pub struct ApiRequest {
+    pub query: ApiQuery,
+    pub response_sender: Sender<ApiResponse>,
+}
+
+#[derive(Debug, Clone)]
+pub enum ApiQuery {
+    Head,
+    BlockByHash(BlockHash),
+    BlockByIndex(u64),
+    Balance(WalletId),
+    PendingTransactions,
+    Peers,
+    SendTransaction(Box<VerifiedTransaction>),
+}
+
+#[derive(Debug, Clone)]
+pub enum ApiResponse {
+    Block(Option<VerifiedBlock>),
+    Balance(u64),
+    Transactions(Vec<VerifiedTransaction>),
+    Peers(Vec<PeerInfo>),
+    TransactionAccepted(TransactionHash),
+    TransactionRejected(String),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PeerInfo {
+    pub session_id: SessionId,
+    pub address: SocketAddr,
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Deserialize)]
+struct RpcRequest {
+    #[serde(default)]
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Serialize)]
+struct RpcResponse {
+    jsonrpc: &'static str,
+    id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<RpcError>,
+}
+
+#[derive(Serialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum BlockParams {
+    Hash(#[serde(deserialize_with = "deserialize_base64_fixed::<'_, _, HASH_LEN>")] BlockHash),
+    Index(u64),
+}
+
+#[derive(Deserialize)]
+struct BalanceParams {
+    #[serde(deserialize_with = "deserialize_wallet_id")]
+    wallet_id: WalletId,
+}
+
+#[derive(Serialize)]
+struct HashedBlock {
+    #[serde(serialize_with = "serialize_base64")]
+    hash: BlockHash,
+    #[serde(flatten)]
+    block: Block,
+}
+
+#[derive(Serialize)]
+struct HashedTransaction {
+    #[serde(serialize_with = "serialize_base64")]
+    hash: TransactionHash,
+    #[serde(flatten)]
+    transaction: Transaction,
+}
+
+impl From<VerifiedBlock> for HashedBlock {
+    fn from(block: VerifiedBlock) -> Self {
+        Self {
+            hash: *block.hash(),
+            block: block.into(),
+        }
+    }
+}
+
+impl From<VerifiedTransaction> for HashedTransaction {
+    fn from(transaction: VerifiedTransaction) -> Self {
+        Self {
+            hash: *transaction.hash(),
+            transaction: transaction.into(),
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Answers JSON-RPC 2.0 requests sent over HTTP, the queries themselves are handled
+/// by the gossip service.
+pub struct ApiService {
+    listener: TcpListener,
+    request_sender: Sender<ApiRequest>,
+}
+
+impl ApiService {
+    pub fn new(config: ApiServiceConfig, request_sender: Sender<ApiRequest>) -> Result<Self> {
+        let listener = TcpListener::bind(&config.listen_address)
+            .with_context(|| format!("failed to listen on {}", config.listen_address))?;
+        info!("api service is listening on {}", config.listen_address);
+        Ok(Self {
+            listener,
+            request_sender,
+        })
+    }
+
+    pub fn run(&mut self) {
+        // A fixed number of workers serve the connections, so slow clients can't exhaust
+        // threads, and the connections beyond the queue are refused.
+        let (stream_sender, stream_receiver) = bounded::<TcpStream>(MAX_QUEUED_CONNECTIONS);
+        for _ in 0..WORKER_COUNT {
+            let stream_receiver = stream_receiver.clone();
+            let request_sender = self.request_sender.clone();
+            thread::spawn(move || {
+                for stream in stream_receiver.iter() {
+                    if let Err(e) = Self::handle_connection(stream, &request_sender) {
+                        debug!("api connection failed: {:#}", e);
+                    }
+                }
+            });
+        }
+
+        for stream in self.listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    error!("failed to accept api connection: {}", e);
+                    continue;
+                }
+            };
+            if let Err(TrySendError::Full(stream)) = stream_sender.try_send(stream) {
+                warn!("too many api connections, refusing a new one");
+                Self::write_response(&stream, "503 Service Unavailable", b"").ok();
+            }
+        }
+    }
+
+    // Every connection serves a single request.
+    fn handle_connection(stream: TcpStream, request_sender: &Sender<ApiRequest>) -> Result<()> {
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        let mut reader = BufReader::new(&stream);
+
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+        let method = request_line
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_string();
+
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line)? == 0 {
+                bail!("connection closed before the end of headers");
+            }
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.trim().eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().context("invalid content length")?;
+                }
+            }
+        }
+
+        if method != "POST" {
+            return Self::write_response(&stream, "405 Method Not Allowed", b"");
+        }
+        if content_length > MAX_BODY_LEN {
+            return Self::write_response(&stream, "413 Payload Too Large", b"");
+        }
+
+        let mut body = vec![0u8; content_length];
+        reader.read_exact(&mut body)?;
+
+        let response = Self::handle_rpc(&body, request_sender);
+        let data = serde_json::to_vec(&response)?;
+        Self::write_response(&stream, "200 OK", &data)
+    }
+
+    fn write_response(mut stream: &TcpStream, status: &str, body: &[u8]) -> Result<()> {
+        let mut head = format!("HTTP/1.1 {}\r\n", status);
+        if !body.is_empty() {
+            head.push_str("Content-Type: application/json\r\n");
+        }
+        if status.starts_with("405") {
+            head.push_str("Allow: POST\r\n");
+        }
+        head.push_str(&format!(
+            "Content-Length: {}\r\nConnection: close\r\n\r\n",
+            body.len()
+        ));
+        stream.write_all(head.as_bytes())?;
+        stream.write_all(body)?;
+        stream.flush()?;
+        Ok(())
+    }
+
+    fn handle_rpc(body: &[u8], request_sender: &Sender<ApiRequest>) -> RpcResponse {
+        let request: RpcRequest = match serde_json::from_slice(body) {
+            Ok(request) => request,
+            Err(e) => {
+                return RpcResponse {
+                    jsonrpc: "2.0",
+                    id: Value::Null,
+                    result: None,
+                    error: Some(RpcError::new(
+                        PARSE_ERROR,
+                        format!("invalid request: {}", e),
+                    )),
+                }
+            }
+        };
+
+        debug!("api request: {}", request.method);
+        let result = Self::parse_query(&request.method, request.params)
+            .and_then(|query| Self::execute(query, request_sender));
+
+        let (result, error) = match result {
+            Ok(result) => (Some(result), None),
+            Err(error) => (None, Some(error)),
+        };
+        RpcResponse {
+            jsonrpc: "2.0",
+            id: request.id,
+            result,
+            error,
+        }
+    }
+
+    fn parse_query(method: &str, params: Value) -> Result<ApiQuery, RpcError> {
+        let query = match method {
+            "head" => ApiQuery::Head,
+            "block" => match Self::parse_params(params)? {
+                BlockParams::Hash(hash) => ApiQuery::BlockByHash(hash),
+                BlockParams::Index(index) => ApiQuery::BlockByIndex(index),
+            },
+            "balance" => {
+                let params: BalanceParams = Self::parse_params(params)?;
+                ApiQuery::Balance(params.wallet_id)
+            }
+            "pending_transactions" => ApiQuery::PendingTransactions,
+            "peers" => ApiQuery::Peers,
+            "send_transaction" => {
+                let transaction: Transaction = Self::parse_params(params)?;
+                let transaction = transaction.verified().map_err(|e| {
+                    RpcError::new(INVALID_PARAMS, format!("invalid transaction: {:#}", e))
+                })?;
+                ApiQuery::SendTransaction(Box::new(transaction))
+            }
+            _ => {
+                return Err(RpcError::new(
+                    METHOD_NOT_FOUND,
+                    format!("unknown method \"{}\"", method),
+                ))
+            }
+        };
+        Ok(query)
+    }
+
+    fn parse_params<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+        serde_json::from_value(params)
+            .map_err(|e| RpcError::new(INVALID_PARAMS, format!("invalid params: {}", e)))
+    }
+
+    fn execute(query: ApiQuery, request_sender: &Sender<ApiRequest>) -> Result<Value, RpcError> {
+        let (response_sender, response_receiver) = bounded(1);
+        request_sender
+            .send(ApiRequest {
+                query,
+                response_sender,
+            })
+            .map_err(|_| RpcError::new(INTERNAL_ERROR, "gossip service is unavailable"))?;
+        let response = response_receiver
+            .recv_timeout(RESPONSE_TIMEOUT)
+            .map_err(|_| RpcError::new(INTERNAL_ERROR, "gossip service didn't respond"))?;
+
+        let result = match response {
+            ApiResponse::Block(block) => serde_json::to_value(block.map(HashedBlock::from)),
+            ApiResponse::Balance(balance) => Ok(json!(balance)),
+            ApiResponse::Transactions(transactions) => serde_json::to_value(
+                transactions
+                    .into_iter()
+                    .map(HashedTransaction::from)
+                    .collect::<Vec<_>>(),
+            ),
+            ApiResponse::Peers(peers) => serde_json::to_value(peers),
+            ApiResponse::TransactionAccepted(hash) => Ok(json!({ "hash": base64::encode(hash) })),
+            ApiResponse::TransactionRejected(reason) => {
+                return Err(RpcError::new(TRANSACTION_REJECTED, reason))
+            }
+        };
+        result.map_err(|e| RpcError::new(INTERNAL_ERROR, format!("failed to serialize: {}", e)))
+    }
+}
@@ -2,14 +2,16 @@
 
 use crate::{
     block_forest::BlockForest,
+    block_store::BlockStore,
     data::{BlockHash, TransactionHash, VerifiedBlock, VerifiedPeerMessage, VerifiedTransaction},
     node::{
+        api_service::{ApiQuery, ApiRequest, ApiResponse, PeerInfo},
         mining_service::MiningInfo,
         peer_service::{PeerCommand, PeerCommandKind, PeerEvent, PeerEventKind, SessionId},
     },
 };
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use crossbeam::{
     channel::{never, tick, Receiver, RecvError, Sender},
     select,
@@ -21,6 +23,7 @@ use serde::{Deserialize, Serialize};
 
 use std::{
     collections::{HashMap, HashSet},
+    net::SocketAddr,
     time::Duration,
 };
 
@@ -38,7 +41,9 @@ pub struct GossipService {
     command_sender: Sender<PeerCommand>,
     block_receiver: Receiver<VerifiedBlock>,
     mining_info_sender: Sender<MiningInfo>,
+    api_request_receiver: Receiver<ApiRequest>,
     block_forest: BlockForest,
+    block_store: Option<BlockStore>,
     sessions_cache: SessionsCache,
 }
 
@@ -46,6 +51,7 @@ pub struct GossipService {
 struct SessionsCache {
     blocks: HashMap<SessionId, HashSet<BlockHash>>,
     txs: HashMap<SessionId, HashSet<TransactionHash>>,
+    addresses: HashMap<SessionId, SocketAddr>,
 }
 
 impl GossipService {
@@ -55,16 +61,27 @@ impl GossipService {
         command_sender: Sender<PeerCommand>,
         block_receiver: Receiver<VerifiedBlock>,
         mining_info_sender: Sender<MiningInfo>,
-    ) -> Self {
-        Self {
+        api_request_receiver: Receiver<ApiRequest>,
+        mut block_store: Option<BlockStore>,
+    ) -> Result<Self> {
+        let block_forest = match block_store.as_mut() {
+            Some(block_store) => block_store
+                .load_forest()
+                .context("failed to restore blocks from storage")?,
+            None => BlockForest::new(),
+        };
+
+        Ok(Self {
             config,
             event_receiver,
             command_sender,
             block_receiver,
             mining_info_sender,
-            block_forest: BlockForest::new(),
+            api_request_receiver,
+            block_forest,
+            block_store,
             sessions_cache: SessionsCache::default(),
-        }
+        })
     }
 
     pub fn run(&mut self) {
@@ -79,6 +96,7 @@ impl GossipService {
                 recv(&self.event_receiver) -> msg => self.handle_peer_event(msg),
                 recv(&self.block_receiver) -> msg => self.spread_mined_block(msg),
                 recv(&request_unknown_ticker) -> _ => self.request_unknown_blocks(),
+                recv(&self.api_request_receiver) -> msg => self.handle_api_request(msg),
             }
             self.send_mining_info();
         }
@@ -118,7 +136,7 @@ impl GossipService {
         trace!("new peer event {:?}", event_kind);
 
         let cmds = match event_kind {
-            PeerEventKind::Connected => self.new_session_cmds(session_id),
+            PeerEventKind::Connected(address) => self.new_session_cmds(session_id, address),
             PeerEventKind::Disconnected => self.terminate_session_cmds(session_id),
             PeerEventKind::NewMessage(msg) => self.new_message_cmds(msg, session_id),
         };
@@ -132,7 +150,8 @@ impl GossipService {
         })
     }
 
-    fn new_session_cmds(&mut self, session_id: SessionId) -> Vec<PeerCommand> {
+    fn new_session_cmds(&mut self, session_id: SessionId, address: SocketAddr) -> Vec<PeerCommand> {
+        self.sessions_cache.addresses.insert(session_id, address);
         self.sessions_cache
             .blocks
             .insert(session_id, HashSet::new());
@@ -176,6 +195,7 @@ impl GossipService {
     fn terminate_session_cmds(&mut self, session_id: SessionId) -> Vec<PeerCommand> {
         self.sessions_cache.blocks.remove(&session_id);
         self.sessions_cache.txs.remove(&session_id);
+        self.sessions_cache.addresses.remove(&session_id);
 
         vec![PeerCommand {
             session_id,
@@ -218,20 +238,22 @@ impl GossipService {
 
     fn add_block_cmnds(&mut self, block_box: Box<VerifiedBlock>) -> Vec<PeerCommand> {
         match self.block_forest.add_block(*block_box.clone()) {
-            Ok(()) => self
-                .sessions_cache
-                .blocks
-                .par_iter_mut()
-                .filter_map(|(session_id, known_blocks)| {
-                    known_blocks.insert(*block_box.hash()).then_some(session_id)
-                })
-                .map(|&session_id| PeerCommand {
-                    session_id,
-                    command_kind: PeerCommandKind::SendMessage(VerifiedPeerMessage::Block(
-                        block_box.clone(),
-                    )),
-                })
-                .collect(),
+            Ok(()) => {
+                self.store_block(&block_box);
+                self.sessions_cache
+                    .blocks
+                    .par_iter_mut()
+                    .filter_map(|(session_id, known_blocks)| {
+                        known_blocks.insert(*block_box.hash()).then_some(session_id)
+                    })
+                    .map(|&session_id| PeerCommand {
+                        session_id,
+                        command_kind: PeerCommandKind::SendMessage(VerifiedPeerMessage::Block(
+                            block_box.clone(),
+                        )),
+                    })
+                    .collect()
+            }
             Err(e) => {
                 error!("new block failed to add: {}", e);
                 vec![]
@@ -278,20 +300,8 @@ impl GossipService {
 
         match self.block_forest.add_transaction(*tx_box.clone()) {
             Ok(()) => {
-                let txs = self
-                    .sessions_cache
-                    .txs
-                    .par_iter_mut()
-                    .filter_map(|(session_id, known_txs)| {
-                        known_txs.insert(*tx_box.hash()).then_some(session_id)
-                    })
-                    .map(|&session_id| PeerCommand {
-                        session_id,
-                        command_kind: PeerCommandKind::SendMessage(
-                            VerifiedPeerMessage::Transaction(tx_box.clone()),
-                        ),
-                    })
-                    .collect::<Vec<PeerCommand>>();
+                self.store_pending_transactions();
+                let txs = self.spread_tx_cmds(tx_box);
                 for tx in txs.iter() {
                     debug!(
                         "add new tx to: {:?} from: {:?}",
@@ -307,6 +317,41 @@ impl GossipService {
         }
     }
 
+    fn spread_tx_cmds(&mut self, tx_box: Box<VerifiedTransaction>) -> Vec<PeerCommand> {
+        self.sessions_cache
+            .txs
+            .par_iter_mut()
+            .filter_map(|(session_id, known_txs)| {
+                known_txs.insert(*tx_box.hash()).then_some(session_id)
+            })
+            .map(|&session_id| PeerCommand {
+                session_id,
+                command_kind: PeerCommandKind::SendMessage(VerifiedPeerMessage::Transaction(
+                    tx_box.clone(),
+                )),
+            })
+            .collect()
+    }
+
+    fn store_block(&mut self, block: &VerifiedBlock) {
+        if let Some(block_store) = self.block_store.as_mut() {
+            if let Err(e) = block_store.append_block(block) {
+                error!("failed to store block: {:#}", e);
+            }
+        }
+        // switching the head changes the pending transactions
+        self.store_pending_transactions();
+    }
+
+    fn store_pending_transactions(&self) {
+        if let Some(block_store) = self.block_store.as_ref() {
+            let pending = self.block_forest.pending_transactions().values();
+            if let Err(e) = block_store.save_pending(pending) {
+                error!("failed to store pending transactions: {:#}", e);
+            }
+        }
+    }
+
     fn requested_block_cmd(
         &self,
         block_hash: BlockHash,
@@ -354,6 +399,76 @@ impl GossipService {
         })
     }
 
+    fn handle_api_request(&mut self, api_request_msg: Result<ApiRequest, RecvError>) {
+        if let Err(e) = api_request_msg {
+            error!("unable to receive api request: {}", e);
+            return;
+        }
+
+        let ApiRequest {
+            query,
+            response_sender,
+        } = api_request_msg.unwrap();
+
+        trace!("new api query {:?}", query);
+
+        let response = match query {
+            ApiQuery::Head => ApiResponse::Block(Some(self.block_forest.head().as_ref().clone())),
+            ApiQuery::BlockByHash(block_hash) => ApiResponse::Block(
+                self.block_forest
+                    .find_block(&block_hash)
+                    .map(|block| block.as_ref().clone()),
+            ),
+            ApiQuery::BlockByIndex(index) => ApiResponse::Block(
+                self.block_forest
+                    .find_block_by_index(index)
+                    .map(|block| block.as_ref().clone()),
+            ),
+            ApiQuery::Balance(wallet_id) => {
+                ApiResponse::Balance(self.block_forest.balance(&wallet_id))
+            }
+            ApiQuery::PendingTransactions => ApiResponse::Transactions(
+                self.block_forest
+                    .pending_transactions()
+                    .values()
+                    .cloned()
+                    .collect(),
+            ),
+            ApiQuery::Peers => ApiResponse::Peers(
+                self.sessions_cache
+                    .addresses
+                    .iter()
+                    .map(|(&session_id, &address)| PeerInfo {
+                        session_id,
+                        address,
+                    })
+                    .collect(),
+            ),
+            ApiQuery::SendTransaction(tx_box) => self.api_tx_response(tx_box),
+        };
+
+        if response_sender.send(response).is_err() {
+            debug!("api request was abandoned");
+        }
+    }
+
+    fn api_tx_response(&mut self, tx_box: Box<VerifiedTransaction>) -> ApiResponse {
+        let tx_hash = *tx_box.hash();
+        if let Err(e) = self.block_forest.add_transaction(*tx_box.clone()) {
+            return ApiResponse::TransactionRejected(format!("{:#}", e));
+        }
+        self.store_pending_transactions();
+
+        self.spread_tx_cmds(tx_box)
+            .into_iter()
+            .for_each(|peer_cmd| {
+                self.command_sender
+                    .send(peer_cmd)
+                    .expect("unable to send new tx comand")
+            });
+        ApiResponse::TransactionAccepted(tx_hash)
+    }
+
     fn spread_mined_block(&mut self, peer_block_msg: Result<VerifiedBlock, RecvError>) {
         if let Err(e) = peer_block_msg {
             error!("unable to receive peer event msg: {}", e);
@@ -43,7 +43,7 @@ pub struct PeerEvent {
 
 #[derive(Debug, Clone)]
 pub enum PeerEventKind {
-    Connected,
+    Connected(SocketAddr),
     Disconnected,
     NewMessage(VerifiedPeerMessage),
 }
@@ -117,12 +117,19 @@ impl PeerService {
         }
     }
     fn add_new_stream(&self, stream: TcpStream) {
+        // The peer may be gone already, then the session is dropped before it's registered.
+        let peer_addr = match stream.peer_addr() {
+            Ok(peer_addr) => peer_addr,
+            Err(err) => {
+                error!("failed to get the address of a new connection: {}", err);
+                return;
+            }
+        };
         let session_id = self.gen_unique_session_id();
 
         info!(
             "new connection: {} with session_id: {}",
-            stream.peer_addr().unwrap().to_string(),
-            session_id
+            peer_addr, session_id
         );
 
         let stream_arc = Arc::new(stream);
@@ -134,24 +141,23 @@ impl PeerService {
             .unwrap()
             .insert(session_id, comm_kind_snd);
 
-        self.init_tcp_read(stream_arc.clone(), session_id);
+        self.init_tcp_read(stream_arc.clone(), session_id, peer_addr);
         self.init_tcp_write(stream_arc.clone(), comm_kind_recv);
 
         self.peer_event_sender
             .send(PeerEvent {
                 session_id,
-                event_kind: PeerEventKind::Connected,
+                event_kind: PeerEventKind::Connected(peer_addr),
             })
-            .expect(
-                format!(
-                    "couldn't send connected event for {}",
-                    stream_arc.peer_addr().unwrap()
-                )
-                .as_str(),
-            );
+            .expect(format!("couldn't send connected event for {}", peer_addr).as_str());
     }
 
-    fn init_tcp_read(&self, stream_arc: Arc<TcpStream>, session_id: SessionId) {
+    fn init_tcp_read(
+        &self,
+        stream_arc: Arc<TcpStream>,
+        session_id: SessionId,
+        peer_addr: SocketAddr,
+    ) {
         let event_sender = self.peer_event_sender.clone();
         thread::spawn(move || {
             let mut r_socket = BufReader::with_capacity(BUF_SIZE, stream_arc.as_ref());
@@ -171,8 +177,7 @@ impl PeerService {
                     if byte == MSG_DELIM {
                         debug!(
                             "message from session_id: {:?}, peer_addr: {:?}",
-                            session_id,
-                            stream_arc.peer_addr().unwrap(),
+                            session_id, peer_addr,
                         );
 
                         // Process the complete message
@@ -180,7 +185,7 @@ impl PeerService {
                             message.clone(),
                             &event_sender,
                             session_id,
-                            stream_arc.peer_addr().unwrap(),
+                            peer_addr,
                         )
                         .is_err()
                         {
@@ -196,10 +201,7 @@ impl PeerService {
                     }
                 }
                 if message.len() >= BUF_SIZE {
-                    error!(
-                        "the incoming message from {} was too large",
-                        stream_arc.peer_addr().unwrap()
-                    );
+                    error!("the incoming message from {} was too large", peer_addr);
                     break;
                 }
 
@@ -2,7 +2,7 @@ use crate::data::WalletId;
 
 use anyhow::{Context, Result};
 use chrono::{DateTime, LocalResult, TimeZone, Utc};
-use rsa::{PublicKeyEncoding, RSAPrivateKey, RSAPublicKey};
+use rsa::{PrivateKeyEncoding, PublicKeyEncoding, RSAPrivateKey, RSAPublicKey};
 use serde::{
     de::{self, Deserializer},
     ser::{self, Serializer},
@@ -11,6 +11,10 @@ use serde::{
 
 ////////////////////////////////////////////////////////////////////////////////
 
+const PEM_LINE_LEN: usize = 64;
+
+////////////////////////////////////////////////////////////////////////////////
+
 fn decode_pkcs8_plaintext(raw: &str) -> Result<Vec<u8>> {
     let der_encoded =
         raw.lines()
@@ -32,6 +36,32 @@ pub fn parse_pkcs8_private(raw: &str) -> Result<RSAPrivateKey> {
     RSAPrivateKey::from_pkcs8(&der_bytes).context("failed to decode pkcs8 bytes")
 }
 
+fn encode_pkcs8_plaintext(label: &str, der_bytes: &[u8]) -> String {
+    let encoded = base64::encode(der_bytes);
+    let mut pem = format!("-----BEGIN {}-----\n", label);
+    for line in encoded.as_bytes().chunks(PEM_LINE_LEN) {
+        // base64 is ASCII, so any chunk of it is a valid string
+        pem.push_str(std::str::from_utf8(line).unwrap());
+        pem.push('\n');
+    }
+    pem.push_str(&format!("-----END {}-----\n", label));
+    pem
+}
+
+pub fn encode_pkcs8_private(key: &RSAPrivateKey) -> Result<String> {
+    let der_bytes = key.to_pkcs8().context("failed to encode key as pkcs8")?;
+    Ok(encode_pkcs8_plaintext("PRIVATE KEY", &der_bytes))
+}
+
+/// The wallet id as it's written in blocks, transactions and configs.
+pub fn encode_wallet_id(wallet: &WalletId) -> Result<String> {
+    let der_bytes = wallet
+        .public_key
+        .to_pkcs8()
+        .context("failed to encode key as pkcs8")?;
+    Ok(base64::encode(der_bytes))
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 
 pub fn serialize_base64<T, S>(array: &T, serializer: S) -> Result<S::Ok, S::Error>
@@ -0,0 +1,170 @@
+use crate::{
+    block_forest::BlockForest,
+    data::{Block, BlockHash, PeerMessage, VerifiedBlock, VerifiedTransaction},
+    util::{encode_pkcs8_private, parse_pkcs8_private},
+};
+
+use anyhow::{bail, Context, Result};
+use rand::thread_rng;
+use rsa::RSAPrivateKey;
+
+use std::{
+    fs,
+    io::{BufRead, BufReader, Write},
+    net::TcpStream,
+    path::Path,
+    time::Duration,
+};
+
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+
+////////////////////////////////////////////////////////////////////////////////
+
+const MSG_DELIM: u8 = 0u8;
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+////////////////////////////////////////////////////////////////////////////////
+
+pub fn generate_key(bits: usize) -> Result<RSAPrivateKey> {
+    RSAPrivateKey::new(&mut thread_rng(), bits).context("failed to generate RSA key")
+}
+
+/// Writes the key in PKCS8, an existing file is never overwritten.
+pub fn write_key(path: &Path, key: &RSAPrivateKey) -> Result<()> {
+    let pem = encode_pkcs8_private(key)?;
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("failed to create {}", path.display()))?;
+    file.write_all(pem.as_bytes())
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+pub fn read_key(path: &Path) -> Result<RSAPrivateKey> {
+    let raw =
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    parse_pkcs8_private(&raw).with_context(|| format!("failed to parse key {}", path.display()))
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// A session with a running node over the peer protocol.
+pub struct NodeClient {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl NodeClient {
+    pub fn connect(address: &str) -> Result<Self> {
+        let stream = TcpStream::connect(address)
+            .with_context(|| format!("failed to connect to {}", address))?;
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        Ok(Self {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+        })
+    }
+
+    pub fn send(&mut self, message: &PeerMessage) -> Result<()> {
+        let mut data = serde_json::to_vec(message).context("failed to serialize message")?;
+        data.push(MSG_DELIM);
+        self.writer
+            .write_all(&data)
+            .context("failed to send message")
+    }
+
+    pub fn recv(&mut self) -> Result<PeerMessage> {
+        let mut data = vec![];
+        self.reader
+            .read_until(MSG_DELIM, &mut data)
+            .context("failed to receive message")?;
+        if data.pop() != Some(MSG_DELIM) {
+            bail!("node closed the connection");
+        }
+        serde_json::from_slice(&data).context("failed to parse message")
+    }
+
+    /// Receives the head block the node sends to every new session.
+    pub fn recv_head(&mut self) -> Result<Block> {
+        loop {
+            if let PeerMessage::Block(block) = self.recv()? {
+                return Ok(*block);
+            }
+        }
+    }
+
+    pub fn request_block(&mut self, hash: &BlockHash) -> Result<Block> {
+        self.send(&PeerMessage::Request { block_hash: *hash })?;
+        loop {
+            if let PeerMessage::Block(block) = self.recv()? {
+                if block.compute_hash() == *hash {
+                    return Ok(*block);
+                }
+            }
+        }
+    }
+
+    /// Waits until the node handles all the messages sent before.
+    pub fn sync(&mut self) -> Result<()> {
+        // The messages of a session are handled in order, so once the node answers
+        // the request, it has handled the previous messages as well.
+        let genesis_hash = *VerifiedBlock::genesis().hash();
+        self.send(&PeerMessage::Request {
+            block_hash: genesis_hash,
+        })?;
+
+        loop {
+            if let PeerMessage::Block(block) = self.recv()? {
+                if block.compute_hash() == genesis_hash {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Downloads the chain from the given head down to the genesis block.
+    pub fn fetch_chain(&mut self, head: Block) -> Result<BlockForest> {
+        let mut blocks = vec![];
+        let mut block = head;
+        while block.index > 0 {
+            let prev_hash = block.prev_hash;
+            blocks.push(block);
+            block = self
+                .request_block(&prev_hash)
+                .with_context(|| format!("failed to fetch block {}", base64::encode(prev_hash)))?;
+        }
+
+        let mut block_forest = BlockForest::new();
+        for block in blocks.into_iter().rev() {
+            let block = block.verified().context("node sent an invalid block")?;
+            block_forest
+                .add_block(block)
+                .context("node sent an invalid block")?;
+        }
+        Ok(block_forest)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Downloads the chain of the node at the given address.
+pub fn fetch_chain(address: &str) -> Result<BlockForest> {
+    let mut client = NodeClient::connect(address)?;
+    let head = client.recv_head()?;
+    client.fetch_chain(head)
+}
+
+/// Sends the transaction to the node at the given address and waits until it is handled.
+/// The node silently discards a transaction if the sender has insufficient funds.
+pub fn submit_transaction(address: &str, tx: &VerifiedTransaction) -> Result<()> {
+    let mut client = NodeClient::connect(address)?;
+    client.recv_head()?;
+    client.send(&PeerMessage::Transaction(Box::new(tx.clone().into())))?;
+    client.sync()
+}
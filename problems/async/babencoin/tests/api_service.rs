@@ -0,0 +1,217 @@
+#[macro_use]
+mod helpers;
+
+use helpers::{generate_private_key, generate_public_key, send_message, sync, wait_for_message};
+
+use babencoin::{
+    data::{PeerMessage, Transaction, VerifiedBlock, VerifiedTransaction, WalletId},
+    node::{self, ApiServiceConfig},
+    util::encode_wallet_id,
+};
+
+use rand::{thread_rng, Rng};
+use serde_json::{json, Value};
+
+use std::{
+    io::{Read, Write},
+    net::TcpStream,
+};
+
+////////////////////////////////////////////////////////////////////////////////
+
+fn api_config() -> (node::Config, String) {
+    let port = thread_rng().gen_range(49152..65536);
+    let address = format!("127.0.0.1:{}", port);
+
+    let mut config = node::Config::default();
+    config.api_service = Some(ApiServiceConfig {
+        listen_address: address.clone(),
+    });
+    (config, address)
+}
+
+fn http_request(address: &str, method: &str, body: &str) -> (String, String) {
+    let mut conn = TcpStream::connect(address).unwrap();
+    write!(
+        conn,
+        "{} / HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+        method,
+        address,
+        body.len(),
+        body
+    )
+    .unwrap();
+
+    let mut response = String::new();
+    conn.read_to_string(&mut response).unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    let status = head.lines().next().unwrap().to_string();
+    (status, body.to_string())
+}
+
+fn call(address: &str, method: &str, params: Value) -> Value {
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": method,
+        "params": params,
+    });
+    let (status, body) = http_request(address, "POST", &request.to_string());
+    assert_eq!(status, "HTTP/1.1 200 OK");
+
+    let response: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(response["jsonrpc"], "2.0");
+    assert_eq!(response["id"], 1);
+    response
+}
+
+fn signed_tx(comment: &str) -> VerifiedTransaction {
+    VerifiedTransaction::sign(
+        &generate_private_key(),
+        generate_public_key().into(),
+        0,
+        0,
+        comment.into(),
+    )
+    .unwrap()
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[test]
+fn blocks() {
+    let (config, address) = api_config();
+    let _env = test_env!("api_blocks", config);
+
+    let genesis_hash = base64::encode(VerifiedBlock::genesis().hash());
+
+    let head = call(&address, "head", Value::Null)["result"].clone();
+    assert_eq!(head["index"], 0);
+    assert_eq!(head["hash"], genesis_hash);
+
+    let by_index = call(&address, "block", json!({ "index": 0 }))["result"].clone();
+    assert_eq!(by_index, head);
+    let by_hash = call(&address, "block", json!({ "hash": genesis_hash }))["result"].clone();
+    assert_eq!(by_hash, head);
+
+    let unknown = call(&address, "block", json!({ "index": 1 }));
+    assert_eq!(unknown["result"], Value::Null);
+    let unknown = call(
+        &address,
+        "block",
+        json!({ "hash": base64::encode([7u8; 64]) }),
+    );
+    assert_eq!(unknown["result"], Value::Null);
+}
+
+#[test]
+fn transactions() {
+    let (config, address) = api_config();
+    let env = test_env!("api_transactions", config);
+    let mut conn = env.connect_to_node().unwrap();
+
+    let from_peer = signed_tx("from peer");
+    send_message(
+        &mut conn,
+        PeerMessage::Transaction(Box::new(from_peer.clone().into())),
+    )
+    .unwrap();
+    sync(&mut conn).unwrap();
+
+    let from_api = signed_tx("from api");
+    let response = call(
+        &address,
+        "send_transaction",
+        serde_json::to_value(Transaction::from(from_api.clone())).unwrap(),
+    );
+    assert_eq!(response["result"]["hash"], base64::encode(from_api.hash()));
+
+    // The transaction is spread to the peers.
+    wait_for_message(&mut conn, 3, |msg| match msg {
+        PeerMessage::Transaction(tx) => tx.compute_hash() == *from_api.hash(),
+        _ => false,
+    })
+    .unwrap();
+
+    let pending = call(&address, "pending_transactions", Value::Null)["result"].clone();
+    let mut hashes = pending
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|tx| tx["hash"].as_str().unwrap().to_string())
+        .collect::<Vec<_>>();
+    hashes.sort();
+    let mut expected = vec![
+        base64::encode(from_peer.hash()),
+        base64::encode(from_api.hash()),
+    ];
+    expected.sort();
+    assert_eq!(hashes, expected);
+
+    // Nobody has funds yet.
+    let insufficient_funds = VerifiedTransaction::sign(
+        &generate_private_key(),
+        generate_public_key().into(),
+        10,
+        0,
+        "rejected".into(),
+    )
+    .unwrap();
+    let response = call(
+        &address,
+        "send_transaction",
+        serde_json::to_value(Transaction::from(insufficient_funds)).unwrap(),
+    );
+    assert_eq!(response["error"]["code"], -32000);
+
+    let mut invalid = Transaction::from(signed_tx("invalid"));
+    invalid.amount = 1;
+    let response = call(
+        &address,
+        "send_transaction",
+        serde_json::to_value(invalid).unwrap(),
+    );
+    assert_eq!(response["error"]["code"], -32602);
+}
+
+#[test]
+fn balance_and_peers() {
+    let (config, address) = api_config();
+    let env = test_env!("api_balance_and_peers", config);
+    let mut conn = env.connect_to_node().unwrap();
+    // Make sure the gossip service knows about the session.
+    sync(&mut conn).unwrap();
+
+    let wallet_id = encode_wallet_id(&WalletId::of_genesis()).unwrap();
+    let response = call(&address, "balance", json!({ "wallet_id": wallet_id }));
+    assert_eq!(response["result"], 0);
+
+    let response = call(&address, "balance", json!({ "wallet_id": "not a key" }));
+    assert_eq!(response["error"]["code"], -32602);
+
+    let peers = call(&address, "peers", Value::Null)["result"].clone();
+    let local_address = conn.local_addr().unwrap().to_string();
+    assert!(peers
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|peer| peer["address"] == local_address.as_str()));
+}
+
+#[test]
+fn invalid_requests() {
+    let (config, address) = api_config();
+    let _env = test_env!("api_invalid_requests", config);
+
+    let response = call(&address, "mine_for_me", Value::Null);
+    assert_eq!(response["error"]["code"], -32601);
+    assert!(response.get("result").is_none());
+
+    let (status, body) = http_request(&address, "POST", "{\"method\": ");
+    assert_eq!(status, "HTTP/1.1 200 OK");
+    let response: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(response["error"]["code"], -32700);
+
+    let (status, _) = http_request(&address, "GET", "");
+    assert_eq!(status, "HTTP/1.1 405 Method Not Allowed");
+}
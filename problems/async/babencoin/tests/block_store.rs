@@ -0,0 +1,158 @@
+mod helpers;
+
+use helpers::{generate_public_key, random_block};
+
+use babencoin::{
+    block_store::{BlockStore, StorageConfig},
+    data::{Block, VerifiedBlock, VerifiedTransaction, WalletId},
+    util::parse_pkcs8_private,
+};
+
+use rsa::RSAPublicKey;
+
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::Path,
+};
+
+////////////////////////////////////////////////////////////////////////////////
+
+fn open_store(path: &Path) -> BlockStore {
+    let config = StorageConfig {
+        path: Some(path.to_path_buf()),
+        sync_writes: false,
+    };
+    BlockStore::open(&config).unwrap().unwrap()
+}
+
+fn make_chain(issuer: WalletId, len: u64) -> Vec<VerifiedBlock> {
+    let mut blocks = vec![];
+    let mut prev = Block::genesis();
+    for index in 1..=len {
+        let mut block = Block::genesis();
+        block.index = index;
+        block.reward = 1000;
+        block.issuer = issuer.clone();
+        block.prev_hash = prev.compute_hash();
+        block.timestamp = prev
+            .timestamp
+            .checked_add_signed(chrono::Duration::minutes(1))
+            .unwrap();
+        blocks.push(block.clone().verified().unwrap());
+        prev = block;
+    }
+    blocks
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[test]
+fn disabled() {
+    let config = StorageConfig::default();
+    assert!(BlockStore::open(&config).unwrap().is_none());
+}
+
+#[test]
+fn restore_chain() {
+    let dir = tempfile::tempdir().unwrap();
+    let chain = make_chain(generate_public_key().into(), 5);
+    let orphan = random_block(20).verified().unwrap();
+
+    {
+        let mut store = open_store(dir.path());
+        for block in chain.iter() {
+            store.append_block(block).unwrap();
+        }
+        store.append_block(&orphan).unwrap();
+        // Known blocks are not stored twice.
+        store.append_block(&chain[0]).unwrap();
+    }
+
+    let mut store = open_store(dir.path());
+    assert!(store.contains(orphan.hash()));
+    assert_eq!(store.read_block(chain[2].hash()).unwrap(), chain[2]);
+
+    let block_forest = store.load_forest().unwrap();
+    assert_eq!(block_forest.head().as_ref(), chain.last().unwrap());
+    assert!(block_forest.find_block(orphan.hash()).is_some());
+    assert!(block_forest
+        .unknown_block_hashes()
+        .contains(&orphan.prev_hash));
+}
+
+#[test]
+fn torn_writes() {
+    let dir = tempfile::tempdir().unwrap();
+    let chain = make_chain(generate_public_key().into(), 4);
+
+    {
+        let mut store = open_store(dir.path());
+        for block in chain.iter().take(3) {
+            store.append_block(block).unwrap();
+        }
+    }
+
+    // The node was killed while writing the next block: half of its record is in
+    // the blocks file, and the index lacks the last entry.
+    let mut blocks_file = OpenOptions::new()
+        .append(true)
+        .open(dir.path().join("blocks.log"))
+        .unwrap();
+    blocks_file.write_all(&[0, 0, 1, 0, b'{']).unwrap();
+    let index_path = dir.path().join("blocks.idx");
+    let index_len = fs::metadata(&index_path).unwrap().len();
+    OpenOptions::new()
+        .write(true)
+        .open(&index_path)
+        .unwrap()
+        .set_len(index_len - 10)
+        .unwrap();
+
+    {
+        let mut store = open_store(dir.path());
+        assert!(store.contains(chain[2].hash()));
+        assert_eq!(store.load_forest().unwrap().head().index, 3);
+        store.append_block(&chain[3]).unwrap();
+    }
+
+    let mut store = open_store(dir.path());
+    assert_eq!(store.load_forest().unwrap().head().as_ref(), &chain[3]);
+}
+
+#[test]
+fn restore_pending_transactions() {
+    let dir = tempfile::tempdir().unwrap();
+    let key = parse_pkcs8_private(include_str!("../data/test.pem")).unwrap();
+    let chain = make_chain(RSAPublicKey::from(key.clone()).into(), 1);
+
+    let tx = VerifiedTransaction::sign(
+        &key,
+        generate_public_key().into(),
+        100,
+        10,
+        "pending".into(),
+    )
+    .unwrap();
+    // The sender has no funds for this one.
+    let invalid_tx = VerifiedTransaction::sign(
+        &key,
+        generate_public_key().into(),
+        10000,
+        10,
+        "invalid".into(),
+    )
+    .unwrap();
+
+    {
+        let mut store = open_store(dir.path());
+        store.append_block(&chain[0]).unwrap();
+        store.save_pending([&tx, &invalid_tx].into_iter()).unwrap();
+    }
+
+    let mut store = open_store(dir.path());
+    let block_forest = store.load_forest().unwrap();
+    let pending = block_forest.pending_transactions();
+    assert_eq!(pending.len(), 1);
+    assert!(pending.contains_key(tx.hash()));
+}
@@ -0,0 +1,83 @@
+#[macro_use]
+mod helpers;
+
+use helpers::{generate_private_key, generate_public_key};
+
+use babencoin::{
+    data::{VerifiedTransaction, WalletId},
+    node,
+    util::{encode_wallet_id, parse_pkcs8_public},
+    wallet::{self, NodeClient},
+};
+
+use std::{thread, time::Duration};
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[test]
+fn key_roundtrip() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("key.pem");
+
+    let key = wallet::generate_key(1024).unwrap();
+    wallet::write_key(&path, &key).unwrap();
+    // An existing key is never overwritten.
+    assert!(wallet::write_key(&path, &key).is_err());
+
+    let read_key = wallet::read_key(&path).unwrap();
+    let wallet_id: WalletId = key.to_public_key().into();
+    assert_eq!(WalletId::from(read_key.to_public_key()), wallet_id);
+
+    let encoded = encode_wallet_id(&wallet_id).unwrap();
+    assert_eq!(
+        WalletId::from(parse_pkcs8_public(&encoded).unwrap()),
+        wallet_id
+    );
+}
+
+#[test]
+fn balance_of_miner() {
+    let miner_key = generate_private_key();
+    let miner_id: WalletId = miner_key.to_public_key().into();
+
+    let mut config = node::Config::default();
+    config.mining_service.thread_count = 1;
+    config.mining_service.max_tx_per_block = 1;
+    config.mining_service.public_key = miner_id.clone();
+
+    let env = test_env!("wallet_balance_of_miner", config);
+    let address = env
+        .connect_to_node()
+        .unwrap()
+        .peer_addr()
+        .unwrap()
+        .to_string();
+
+    // The node mines only blocks with transactions.
+    let tx = VerifiedTransaction::sign(
+        &generate_private_key(),
+        generate_public_key().into(),
+        0,
+        0,
+        "mine me".into(),
+    )
+    .unwrap();
+    wallet::submit_transaction(&address, &tx).unwrap();
+
+    let mut head = None;
+    for _ in 0..50 {
+        let mut client = NodeClient::connect(&address).unwrap();
+        let block = client.recv_head().unwrap();
+        if block.index > 0 {
+            head = Some(block);
+            break;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    let head = head.expect("node didn't mine a block");
+
+    let block_forest = wallet::fetch_chain(&address).unwrap();
+    assert!(block_forest.head().index >= head.index);
+    assert!(block_forest.balance(&miner_id) >= head.reward);
+    assert_eq!(block_forest.balance(&generate_public_key().into()), 0);
+}
@@ -0,0 +1,39 @@
+use crate::repository::{problem::Problem, repo::Repository};
+use anyhow::{bail, Result};
+use std::path::Path;
+
+/// Validates `.config.yml` of the problem at `path` or, with `all`, of every problem
+/// in the repository. All the errors are printed before failing.
+pub fn check_config(path: &Path, all: bool) -> Result<()> {
+    let repository = Repository::from_path(path)?;
+    let problems = if all {
+        repository.problems()?
+    } else {
+        vec![repository.problem_from_path(path)?]
+    };
+    let mut invalid = 0;
+    for problem in &problems {
+        match check_problem(problem) {
+            Ok(()) => eprintln!("{}: ok", problem.branch_name()),
+            Err(err) => {
+                invalid += 1;
+                eprintln!("{}: {err:#}", problem.branch_name());
+            }
+        }
+    }
+    if invalid > 0 {
+        bail!("{invalid} of {} configs are invalid", problems.len())
+    }
+    Ok(())
+}
+
+fn check_problem(problem: &Problem) -> Result<()> {
+    let config = problem.config()?;
+    if let Some(pattern) = config.get_unmatched_patterns().first() {
+        bail!("allowed pattern \"{pattern}\" matches no files")
+    }
+    if config.get_steps().is_empty() {
+        bail!("config has no steps")
+    }
+    Ok(())
+}
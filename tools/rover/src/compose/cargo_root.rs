@@ -1,48 +1,208 @@
-use super::config::Config;
-use anyhow::{Context, Result};
-use std::{
-    fs,
-    path::{Path, PathBuf},
+use super::{
+    config::Config,
+    tree::{Tree, TreeFile},
 };
+use anyhow::{Context, Result};
+use std::{fs, path::Path};
+use toml_edit::{Array, DocumentMut, Item, Table, Value};
 
-pub fn cargo_root(output: &Path, config: &Config) -> Result<()> {
-    let problems: Vec<_> = config
-        .get_problems()
-        .iter()
-        .map(|path| {
-            let path = PathBuf::from("problems").join(path);
-            path.to_str().unwrap().to_string()
-        })
-        .collect();
-    let tools: Vec<_> = config
-        .get_tools()
-        .iter()
-        .map(|path| {
-            let path = PathBuf::from("tools").join(path);
-            path.to_str().unwrap().to_string()
-        })
-        .collect();
-    let add_to_toml: Vec<_> = config
-        .get_add_to_toml()
+/// Derives the workspace manifest of the output from the one of the input: the members
+/// are filtered per the config, everything else including comments is kept.
+pub fn cargo_root(input: &Path, tree: &mut Tree, config: &Config) -> Result<()> {
+    let source = input.join("Cargo.toml");
+    let mut manifest = if source.exists() {
+        fs::read_to_string(&source)
+            .with_context(|| format!("failed to read {source:?}"))?
+            .parse::<DocumentMut>()
+            .with_context(|| format!("failed to parse {source:?}"))?
+    } else {
+        DocumentMut::new()
+    };
+    let members: Vec<_> = std::iter::empty()
+        .chain(
+            config
+                .get_problems()
+                .iter()
+                .map(|path| Path::new("problems").join(path)),
+        )
+        .chain(
+            config
+                .get_tools()
+                .iter()
+                .map(|path| Path::new("tools").join(path)),
+        )
+        .chain(config.get_add_to_toml().iter().cloned())
+        .map(|path| path.to_str().map(str::to_string).context("non-utf-8 path"))
+        .collect::<Result<_>>()?;
+
+    let workspace = manifest
+        .entry("workspace")
+        .or_insert(Item::Table(Table::new()))
+        .as_table_mut()
+        .context("\"workspace\" in Cargo.toml is not a table")?;
+    let array = workspace
+        .entry("members")
+        .or_insert(Item::Value(Value::Array(Array::new())))
+        .as_array_mut()
+        .context("\"workspace.members\" in Cargo.toml is not an array")?;
+    set_members(array, &members);
+    if let Some(default_members) = workspace
+        .get_mut("default-members")
+        .and_then(Item::as_array_mut)
+    {
+        let defaults: Vec<_> = default_members
+            .iter()
+            .filter_map(Value::as_str)
+            .filter(|member| members.iter().any(|m| m == member))
+            .map(str::to_string)
+            .collect();
+        set_members(default_members, &defaults);
+    }
+
+    tree.insert(
+        "Cargo.toml".into(),
+        TreeFile {
+            content: manifest.to_string().into_bytes(),
+            permissions: None,
+        },
+    );
+    Ok(())
+}
+
+/// Keeps the members of the array from `members` with their comments and appends the rest
+/// one per line. An array written on one line stays so unless members are appended.
+fn set_members(array: &mut Array, members: &[String]) {
+    let mut multiline = array.trailing().as_str().is_some_and(|s| s.contains('\n'))
+        || array.iter().any(|member| {
+            member
+                .decor()
+                .prefix()
+                .and_then(|prefix| prefix.as_str())
+                .is_some_and(|prefix| prefix.contains('\n'))
+        });
+    array.retain(|member| {
+        member
+            .as_str()
+            .is_some_and(|member| members.iter().any(|m| m == member))
+    });
+    if !multiline {
+        if let Some(first) = array.get_mut(0) {
+            first.decor_mut().set_prefix("");
+        }
+    }
+    let present: Vec<_> = array
         .iter()
-        .map(|path| path.to_str().unwrap().to_string())
+        .filter_map(Value::as_str)
+        .map(str::to_string)
         .collect();
-    let content = format!(
-        r#"[workspace]
+    for member in members {
+        if present.contains(member) {
+            continue;
+        }
+        array.push(member.as_str());
+        let last = array.len() - 1;
+        array
+            .get_mut(last)
+            .unwrap()
+            .decor_mut()
+            .set_prefix("\n    ");
+        multiline = true;
+    }
+    if multiline {
+        array.set_trailing_comma(true);
+        array.set_trailing("\n");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, process};
+
+    fn compose(name: &str, manifest: Option<&str>, problems: &[&str]) -> String {
+        let input = env::temp_dir().join(format!("rover-test-{}-{name}", process::id()));
+        let _ = fs::remove_dir_all(&input);
+        fs::create_dir_all(&input).unwrap();
+        if let Some(manifest) = manifest {
+            fs::write(input.join("Cargo.toml"), manifest).unwrap();
+        }
+        let config: Config = serde_yaml::from_str(&format!(
+            "problems: [{}]\ntools: [rover]\ncopy: []\nskip-entries: []\nadd-to-toml: []\ndo-not-delete: []\n",
+            problems.join(", ")
+        ))
+        .unwrap();
+        let mut tree = Tree::default();
+        cargo_root(&input, &mut tree, &config).unwrap();
+        fs::remove_dir_all(&input).unwrap();
+        let (_, file) = tree.files().next().unwrap();
+        let manifest = String::from_utf8(file.content.clone()).unwrap();
+        manifest.parse::<DocumentMut>().unwrap();
+        manifest
+    }
+
+    #[test]
+    fn keeps_the_rest_of_the_manifest() {
+        let manifest = compose(
+            "cargo-root-rest",
+            Some(
+                "# The course workspace.
+[workspace]
+resolver = \"2\"
 members = [
-    # Problems
-    "{}",
+    # Intro.
+    \"problems/intro/sum\",
+    \"problems/intro/private\",
+    \"tools/rover\",
+]
+default-members = [\"problems/intro/private\", \"tools/rover\"]
 
-    # Tools
-    "{}",
+[workspace.dependencies]
+anyhow = \"1.0\"
 
-    # Additional
-    "{}",
+[profile.release]
+debug = true # For profiling.
+",
+            ),
+            &["intro/sum", "intro/new"],
+        );
+        assert_eq!(
+            manifest,
+            "# The course workspace.
+[workspace]
+resolver = \"2\"
+members = [
+    # Intro.
+    \"problems/intro/sum\",
+    \"tools/rover\",
+    \"problems/intro/new\",
 ]
-"#,
-        problems.join("\",\n    \""),
-        tools.join("\",\n    \""),
-        add_to_toml.join("\",\n    \"")
-    );
-    fs::write(output.join("Cargo.toml"), content).context("failed to write Cargo.toml")
+default-members = [\"tools/rover\"]
+
+[workspace.dependencies]
+anyhow = \"1.0\"
+
+[profile.release]
+debug = true # For profiling.
+"
+        );
+    }
+
+    #[test]
+    fn creates_the_manifest() {
+        let manifest = compose("cargo-root-new", None, &["intro/sum"]);
+        assert_eq!(
+            manifest,
+            "[workspace]\nmembers = [\n    \"problems/intro/sum\",\n    \"tools/rover\",\n]\n"
+        );
+    }
+
+    #[test]
+    fn empty_members() {
+        let mut array = Array::new();
+        array.push("problems/intro/sum");
+        set_members(&mut array, &[]);
+        let manifest = format!("members = {array}\n");
+        let parsed = manifest.parse::<DocumentMut>().unwrap();
+        assert!(parsed["members"].as_array().unwrap().is_empty());
+    }
 }
@@ -1,4 +1,4 @@
-use anyhow::{bail, Context, Result};
+use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::{
     fs::File,
@@ -19,13 +19,7 @@ pub struct Config {
 impl Config {
     pub fn from_yml(path: &Path) -> Result<Self> {
         let file = File::open(path).context("no yml file with config")?;
-        let config: Config = serde_yaml::from_reader(file).context("cannot read file from yml")?;
-        for entry in config.get_do_not_delete() {
-            if entry.iter().count() != 1 {
-                bail!("compose doesn't support do-not-delete on paths, only directories or files: {entry:?}")
-            }
-        }
-        Ok(config)
+        serde_yaml::from_reader(file).context("cannot read file from yml")
     }
 
     pub fn get_problems(&self) -> &[PathBuf] {
@@ -1,19 +1,22 @@
-use super::file::process_file;
+use super::{file::process_file, tree::Tree};
 use anyhow::{Context, Result};
 use std::{fs, path::Path};
 
-pub fn process_dir(input: &Path, output: &Path) -> Result<()> {
+/// Processes the files of the directory recursively into `tree` at `relative`.
+/// Returns whether anything was hidden.
+pub fn process_dir(input: &Path, relative: &Path, tree: &mut Tree) -> Result<bool> {
+    let mut stubbed = false;
     let dir = fs::read_dir(input).with_context(|| format!("failed to read directory {input:?}"))?;
     for entry in dir {
         let input = entry
             .with_context(|| format!("failed to read entry in directory {input:?}"))?
             .path();
-        let output = output.join(input.file_name().unwrap());
-        if input.is_dir() {
-            process_dir(&input, &output)?;
+        let relative = relative.join(input.file_name().unwrap());
+        stubbed |= if input.is_dir() {
+            process_dir(&input, &relative, tree)?
         } else {
-            process_file(&input, &output)?;
-        }
+            process_file(&input, &relative, tree)?
+        };
     }
-    Ok(())
+    Ok(stubbed)
 }
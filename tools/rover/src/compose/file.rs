@@ -1,168 +1,545 @@
+use super::tree::{Tree, TreeFile};
 use anyhow::{bail, Context, Result};
 use std::{fs, path::Path};
 
-#[allow(clippy::enum_variant_names)]
-enum TokenKind {
+const DEFAULT_HINT: &str = "TODO: your code goes here.";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum CommentStyle {
+    Slashes,
+    Hash,
+}
+
+impl CommentStyle {
+    fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "rs" => Some(Self::Slashes),
+            "toml" | "yml" | "yaml" | "py" => Some(Self::Hash),
+            _ => None,
+        }
+    }
+
+    fn prefix(self) -> &'static str {
+        match self {
+            Self::Slashes => "//",
+            Self::Hash => "#",
+        }
+    }
+}
+
+enum MarkerKind {
     Private,
     BeginPrivate,
     EndPrivate,
+    /// `replace_with { text }` on a single line or `replace_with {` opening a block.
+    ReplaceWith(Option<String>),
+    EndReplace,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
-enum TokenProperty {
-    NoHint,
-    Unimplemented,
+#[derive(Default)]
+struct Properties {
+    no_hint: bool,
+    unimplemented: bool,
+    hint: Option<String>,
 }
 
-struct Token {
-    kind: TokenKind,
-    properties: Vec<TokenProperty>,
+struct Marker {
+    kind: MarkerKind,
+    properties: Properties,
 }
 
-fn parse_token(line: &str) -> Result<Option<Token>> {
-    let comment = match line.find("//") {
+fn parse_marker(line: &str, style: CommentStyle) -> Result<Option<Marker>> {
+    let comment = match line.find(style.prefix()) {
         Some(pos) => &line[pos..],
         None => return Ok(None),
     };
 
     let cmd = match comment.find("compose::") {
-        Some(pos) => &comment[pos + "compose::".len()..],
+        Some(pos) => comment[pos + "compose::".len()..].trim_end(),
         None => return Ok(None),
     };
 
-    let kind = if cmd.starts_with("private") {
-        TokenKind::Private
-    } else if cmd.starts_with("begin_private") {
-        TokenKind::BeginPrivate
-    } else if cmd.starts_with("end_private") {
-        TokenKind::EndPrivate
+    let (kind, rest) = if let Some(rest) = cmd.strip_prefix("begin_private") {
+        (MarkerKind::BeginPrivate, rest)
+    } else if let Some(rest) = cmd.strip_prefix("end_private") {
+        (MarkerKind::EndPrivate, rest)
+    } else if let Some(rest) = cmd.strip_prefix("private") {
+        (MarkerKind::Private, rest)
+    } else if let Some(rest) = cmd.strip_prefix("replace_with") {
+        let body = match rest.trim_start().strip_prefix('{') {
+            Some(body) => body.trim(),
+            None => bail!("expected '{{' after 'replace_with'"),
+        };
+        let text = if body.is_empty() {
+            None
+        } else if let Some(text) = body.strip_suffix('}') {
+            Some(text.trim().to_string())
+        } else {
+            bail!("unclosed '{{' in 'replace_with'")
+        };
+        (MarkerKind::ReplaceWith(text), "")
+    } else if let Some(rest) = cmd.strip_prefix('}') {
+        (MarkerKind::EndReplace, rest)
     } else {
         bail!("unknown compose command: {}", cmd);
     };
 
-    let properties_str = match cmd.find('(') {
-        Some(pos) => {
-            if !cmd.trim_end().ends_with(')') {
-                bail!("unclosed '('");
+    let properties = match kind {
+        MarkerKind::Private | MarkerKind::BeginPrivate if rest.starts_with('(') => {
+            match rest[1..].strip_suffix(')') {
+                Some(properties) => parse_properties(properties)?,
+                None => bail!("unclosed '('"),
             }
-            &cmd[pos + 1..cmd.trim_end().len() - 1]
         }
-        None => "",
+        _ if rest.is_empty() => Properties::default(),
+        _ => bail!("unexpected '{rest}' after compose command"),
     };
+    if properties.no_hint && properties.hint.is_some() {
+        bail!("'no_hint' conflicts with 'hint'");
+    }
+    if properties.unimplemented && style != CommentStyle::Slashes {
+        bail!("'unimplemented' is supported only in Rust files");
+    }
+
+    Ok(Some(Marker { kind, properties }))
+}
 
-    let mut properties = vec![];
-    for prop in properties_str.split_inclusive(',') {
-        match prop {
-            "no_hint" => properties.push(TokenProperty::NoHint),
-            "unimplemented" => properties.push(TokenProperty::Unimplemented),
-            s => bail!("unknown property: {}", s),
+/// Parses a list like `no_hint, hint = "text with \"quotes\""`.
+fn parse_properties(src: &str) -> Result<Properties> {
+    let mut properties = Properties::default();
+    let mut chars = src.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let mut name = String::new();
+        while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '_') {
+            name.push(c);
+        }
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let value = if chars.next_if_eq(&'=').is_some() {
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+            if chars.next() != Some('"') {
+                bail!("value of property '{name}' must be a string in double quotes");
+            }
+            let mut value = String::new();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some(c @ ('"' | '\\')) => value.push(c),
+                        _ => bail!("unknown escape in value of property '{name}'"),
+                    },
+                    Some(c) => value.push(c),
+                    None => bail!("unclosed string in value of property '{name}'"),
+                }
+            }
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+            Some(value)
+        } else {
+            None
+        };
+        match (name.as_str(), value) {
+            ("", None) if chars.peek().is_none() => break,
+            ("no_hint", None) => properties.no_hint = true,
+            ("unimplemented", None) => properties.unimplemented = true,
+            ("hint", Some(hint)) => properties.hint = Some(hint),
+            ("hint", None) => bail!("property 'hint' needs a value: hint = \"...\""),
+            (name, _) => bail!("unknown property: '{name}'"),
+        }
+        match chars.next() {
+            Some(',') => continue,
+            None => break,
+            Some(c) => bail!("unexpected '{c}' in properties"),
         }
     }
+    Ok(properties)
+}
 
-    Ok(Some(Token { kind, properties }))
+/// A private region: the lines of `begin..end` are hidden.
+struct Region {
+    begin: usize,
+    end: usize,
+    properties: Properties,
+    replacement: Option<Vec<String>>,
 }
 
-fn find_token(lines: &[&str], start: usize) -> Result<Option<(usize, Token)>> {
-    for (i, line) in lines[start..].iter().enumerate() {
-        let mb_token = parse_token(line)
-            .with_context(|| format!("failed to parse token on line {}", i + 1))?;
-        if let Some(token) = mb_token {
-            return Ok(Some((i + start, token)));
+struct Composer<'a> {
+    lines: Vec<&'a str>,
+    markers: Vec<Option<Marker>>,
+    style: CommentStyle,
+}
+
+impl<'a> Composer<'a> {
+    fn new(src: &'a str, style: CommentStyle) -> Result<Self> {
+        let lines: Vec<_> = src.lines().collect();
+        let markers = lines
+            .iter()
+            .enumerate()
+            .map(|(i, line)| {
+                parse_marker(line, style)
+                    .with_context(|| format!("failed to parse marker on line {}", i + 1))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            lines,
+            markers,
+            style,
+        })
+    }
+
+    fn compose(mut self) -> Result<String> {
+        let mut dst = String::new();
+        let mut pos = 0;
+        while pos < self.lines.len() {
+            let region = match self.markers[pos].take() {
+                None => {
+                    dst += self.lines[pos];
+                    dst += "\n";
+                    pos += 1;
+                    continue;
+                }
+                Some(Marker {
+                    kind: MarkerKind::Private,
+                    properties,
+                }) => Region {
+                    begin: pos,
+                    end: pos + 1,
+                    properties,
+                    replacement: None,
+                },
+                Some(Marker {
+                    kind: MarkerKind::ReplaceWith(Some(text)),
+                    ..
+                }) => Region {
+                    begin: pos,
+                    end: pos + 1,
+                    properties: Properties::default(),
+                    replacement: Some(vec![self.indent(pos) + &text]),
+                },
+                Some(Marker {
+                    kind: MarkerKind::BeginPrivate,
+                    properties,
+                }) => self.find_region(pos, properties)?,
+                Some(Marker {
+                    kind: MarkerKind::ReplaceWith(None),
+                    ..
+                }) => bail!(
+                    "'replace_with' block on line {} is outside of a private region",
+                    pos + 1
+                ),
+                Some(Marker {
+                    kind: MarkerKind::EndPrivate,
+                    ..
+                }) => bail!("unpaired 'end_private' on line {}", pos + 1),
+                Some(Marker {
+                    kind: MarkerKind::EndReplace,
+                    ..
+                }) => bail!("unpaired 'compose::}}' on line {}", pos + 1),
+            };
+            pos = self.write_region(&mut dst, region)?;
         }
+        Ok(dst)
     }
-    Ok(None)
-}
 
-fn process_source(src: String) -> Result<String> {
-    let mut dst = String::new();
-
-    let lines = src.lines().collect::<Vec<_>>();
-    let mut next_pos = 0;
-    while let Some((begin, token)) = find_token(&lines, next_pos)? {
-        let end = match token.kind {
-            TokenKind::EndPrivate => bail!("unpaired 'end_private' on line {}", begin + 1),
-            TokenKind::Private => begin + 1,
-            TokenKind::BeginPrivate => {
-                let mut pos = begin + 1;
-                let mut mb_end: Option<usize> = None;
-                while let Some((k, token)) = find_token(&lines, pos)? {
-                    match token.kind {
-                        TokenKind::BeginPrivate => {
-                            bail!("nested 'begin_private' on line {}", k + 1)
-                        }
-                        TokenKind::Private => pos = k + 1,
-                        TokenKind::EndPrivate => {
-                            mb_end = Some(k);
-                            break;
+    /// Finds `end_private` of the region starting at `begin`, skipping nested regions.
+    fn find_region(&self, begin: usize, properties: Properties) -> Result<Region> {
+        let mut depth = 1;
+        let mut replacement = None;
+        let mut pos = begin + 1;
+        while pos < self.lines.len() {
+            match self.markers[pos].as_ref().map(|marker| &marker.kind) {
+                Some(MarkerKind::BeginPrivate) => depth += 1,
+                Some(MarkerKind::EndPrivate) => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(Region {
+                            begin,
+                            end: pos + 1,
+                            properties,
+                            replacement,
+                        });
+                    }
+                }
+                Some(MarkerKind::ReplaceWith(text)) => {
+                    let (lines, end) = match text {
+                        Some(text) => (vec![self.indent(pos) + text], pos),
+                        None => self.replacement_block(pos)?,
+                    };
+                    // The replacements of nested regions are hidden with the outer region.
+                    if depth == 1 {
+                        if replacement.is_some() {
+                            bail!(
+                                "second 'replace_with' in private region on line {}",
+                                pos + 1
+                            );
                         }
+                        replacement = Some(lines);
                     }
+                    pos = end;
                 }
-                match mb_end {
-                    Some(end) => end + 1,
-                    None => bail!("unclosed 'begin_private' on line {}", begin + 1),
+                Some(MarkerKind::EndReplace) => {
+                    bail!("unpaired 'compose::}}' on line {}", pos + 1)
                 }
+                Some(MarkerKind::Private) | None => {}
             }
-        };
-
-        #[allow(clippy::needless_range_loop)]
-        for i in next_pos..begin {
-            dst += lines[i];
-            dst += "\n";
+            pos += 1;
         }
+        bail!("unclosed 'begin_private' on line {}", begin + 1)
+    }
 
-        let no_hint = token.properties.contains(&TokenProperty::NoHint);
-        let unimpl = token.properties.contains(&TokenProperty::Unimplemented);
-        if no_hint {
-            if begin > 0
-                && lines[begin - 1].trim().is_empty()
-                && end < lines.len()
-                && lines[end].trim().is_empty()
-            {
-                next_pos = end + 1;
-            } else {
-                next_pos = end;
+    /// Uncomments the lines of the block opened on line `begin`, returns them and the line
+    /// of the closing marker.
+    fn replacement_block(&self, begin: usize) -> Result<(Vec<String>, usize)> {
+        let prefix = self.style.prefix();
+        let mut lines = Vec::new();
+        for pos in begin + 1..self.lines.len() {
+            match &self.markers[pos] {
+                Some(Marker {
+                    kind: MarkerKind::EndReplace,
+                    ..
+                }) => return Ok((lines, pos)),
+                Some(_) => bail!("compose command inside 'replace_with' on line {}", pos + 1),
+                None => {}
             }
-        } else {
-            let mut insert_line = |line: &str| {
-                for c in lines[begin].chars() {
-                    if c.is_whitespace() {
-                        dst.push(c);
-                    } else {
-                        break;
-                    }
-                }
-                dst.push_str(line);
+            let line = self.lines[pos];
+            let content = line.trim_start();
+            let Some(uncommented) = content.strip_prefix(prefix) else {
+                bail!(
+                    "line {} of 'replace_with' block must be commented with '{prefix}'",
+                    pos + 1
+                );
             };
+            let uncommented = uncommented.strip_prefix(' ').unwrap_or(uncommented);
+            let indent = &line[..line.len() - content.len()];
+            lines.push(if uncommented.is_empty() {
+                String::new()
+            } else {
+                indent.to_string() + uncommented
+            });
+        }
+        bail!("unclosed 'replace_with' block on line {}", begin + 1)
+    }
 
-            insert_line("// TODO: your code goes here.\n");
-            if unimpl {
-                insert_line("unimplemented!()\n");
+    /// Writes what replaces the region and returns the line to continue from.
+    fn write_region(&self, dst: &mut String, region: Region) -> Result<usize> {
+        let properties = &region.properties;
+        if let Some(replacement) = region.replacement {
+            if properties.no_hint || properties.unimplemented || properties.hint.is_some() {
+                bail!(
+                    "private region on line {} has both 'replace_with' and hint properties",
+                    region.begin + 1
+                );
+            }
+            for line in replacement {
+                *dst += &line;
+                *dst += "\n";
             }
+            return Ok(region.end);
+        }
+        if properties.no_hint {
+            let surrounded_by_blank = region.begin > 0
+                && self.lines[region.begin - 1].trim().is_empty()
+                && region.end < self.lines.len()
+                && self.lines[region.end].trim().is_empty();
+            return Ok(region.end + surrounded_by_blank as usize);
+        }
+        let indent = self.indent(region.begin);
+        let hint = properties.hint.as_deref().unwrap_or(DEFAULT_HINT);
+        *dst += &format!("{indent}{} {hint}\n", self.style.prefix());
+        if properties.unimplemented {
+            *dst += &format!("{indent}unimplemented!()\n");
+        }
+        Ok(region.end)
+    }
 
-            next_pos = end;
+    fn indent(&self, pos: usize) -> String {
+        self.lines[pos]
+            .chars()
+            .take_while(|c| c.is_whitespace())
+            .collect()
+    }
+}
+
+fn process_source(src: &str, style: CommentStyle) -> Result<String> {
+    Composer::new(src, style)?.compose()
+}
+
+/// Puts the file into `tree` at `relative` hiding its private regions. Returns whether
+/// anything was hidden.
+pub fn process_file(input: &Path, relative: &Path, tree: &mut Tree) -> Result<bool> {
+    let permissions = fs::metadata(input)
+        .with_context(|| format!("failed to read metadata of {:?}", input))?
+        .permissions();
+    let content = fs::read(input).with_context(|| format!("failed to read file {:?}", input))?;
+    let (content, hidden) = match CommentStyle::from_path(input) {
+        Some(style) => {
+            let source = String::from_utf8(content)
+                .with_context(|| format!("file {:?} is not valid utf-8", input))?;
+            let new_source = process_source(&source, style)
+                .with_context(|| format!("failed to process file {:?}", input))?;
+            let hidden = new_source != source;
+            (new_source.into_bytes(), hidden)
         }
+        None => (content, false),
+    };
+    tree.insert(
+        relative.to_path_buf(),
+        TreeFile {
+            content,
+            permissions: Some(permissions),
+        },
+    );
+    Ok(hidden)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The markers of the fixtures are written as `@`, so that composing rover itself
+    /// doesn't take them for real ones.
+    fn compose(src: &str) -> Result<String> {
+        process_source(
+            &src.replace('@', concat!("compose", "::")),
+            CommentStyle::Slashes,
+        )
     }
 
-    for line in &lines[next_pos..] {
-        dst += line;
-        dst += "\n";
+    fn compose_err(src: &str) -> String {
+        format!("{:#}", compose(src).unwrap_err())
     }
 
-    Ok(dst)
+    #[test]
+    fn private_region() {
+        let src = "fn f() -> i32 {
+    // @begin_private(unimplemented)
+    42
+    // @end_private
 }
+";
+        assert_eq!(
+            compose(src).unwrap(),
+            "fn f() -> i32 {
+    // TODO: your code goes here.
+    unimplemented!()
+}
+"
+        );
+    }
 
-pub fn process_file(input: &Path, output: &Path) -> Result<()> {
-    let out_dir = output.parent().unwrap();
-    fs::create_dir_all(out_dir).context("failed to create directory")?;
-    if input.to_str().map(|s| s.ends_with(".rs")).unwrap_or(false) {
-        let content = fs::read_to_string(input)
-            .with_context(|| format!("failed to read file {:?}", input))?;
-        let new_content = process_source(content)
-            .with_context(|| format!("failed to process file {:?}", input))?;
-        fs::write(output, new_content).with_context(|| format!("failed to write file {:?}", input))
-    } else {
-        fs::copy(input, output)
-            .map(|_| ())
-            .with_context(|| format!("failed to copy {:?} to {:?}", input, output))
+    #[test]
+    fn nested_regions() {
+        let src = "a
+// @begin_private(hint = \"outer\")
+b
+    // @begin_private
+    c
+    // @replace_with { inner }
+    // @end_private
+d
+// @end_private
+e // @private
+f
+";
+        assert_eq!(
+            compose(src).unwrap(),
+            "a
+// outer
+// TODO: your code goes here.
+f
+"
+        );
+    }
+
+    #[test]
+    fn replace_with_block() {
+        let src = "    // @begin_private
+    solution();
+    // @replace_with {
+    // todo!();
+    //
+    // }
+    // @}
+    // @end_private
+";
+        assert_eq!(compose(src).unwrap(), "    todo!();\n\n    }\n");
+    }
+
+    #[test]
+    fn unclosed_region() {
+        let src = "a
+// @begin_private
+b
+// @begin_private
+c
+// @end_private
+";
+        assert_eq!(compose_err(src), "unclosed 'begin_private' on line 2");
+    }
+
+    #[test]
+    fn line_numbers() {
+        assert_eq!(
+            compose_err("a\nb\n// @end_private\n"),
+            "unpaired 'end_private' on line 3"
+        );
+        assert_eq!(
+            compose_err("// @begin_private\n// @replace_with {\n// x\n// @end_private\n"),
+            "compose command inside 'replace_with' on line 4"
+        );
+        assert_eq!(
+            compose_err("a\n// @begin_private(no_hint, hint = \"x\")\n// @end_private\n"),
+            "failed to parse marker on line 2: 'no_hint' conflicts with 'hint'"
+        );
+    }
+
+    #[test]
+    fn trailing_text() {
+        let src = "// @begin_private\nx\n// @end_private.\n";
+        assert_eq!(
+            compose_err(src),
+            "failed to parse marker on line 3: unexpected '.' after compose command"
+        );
+        assert_eq!(
+            compose_err("// @private and more\n"),
+            "failed to parse marker on line 1: unexpected ' and more' after compose command"
+        );
+    }
+
+    #[test]
+    fn no_hint_removes_blank_line() {
+        let src = "a
+
+// @begin_private(no_hint)
+b
+// @end_private
+
+c
+";
+        assert_eq!(compose(src).unwrap(), "a\n\nc\n");
+    }
+
+    #[test]
+    fn composes_itself() {
+        let src = include_str!("file.rs");
+        assert_eq!(process_source(src, CommentStyle::Slashes).unwrap(), src);
+    }
+
+    /// Rover is composed into the public repository as is, so its sources must have no
+    /// markers, e.g. in the templates of `new-problem`.
+    #[test]
+    fn rover_sources_have_no_markers() {
+        let mut dirs = vec![Path::new(env!("CARGO_MANIFEST_DIR")).join("src")];
+        while let Some(dir) = dirs.pop() {
+            for entry in fs::read_dir(dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    dirs.push(path);
+                } else if path.extension().is_some_and(|ext| ext == "rs") {
+                    let src = fs::read_to_string(&path).unwrap();
+                    let composed = process_source(&src, CommentStyle::Slashes);
+                    assert_eq!(composed.ok().as_ref(), Some(&src), "{path:?}");
+                }
+            }
+        }
     }
 }
@@ -3,6 +3,8 @@ pub mod config;
 mod dir;
 mod file;
 mod process;
-mod prune;
 pub mod run_compose;
 mod skip;
+mod sync;
+mod tree;
+pub mod verify;
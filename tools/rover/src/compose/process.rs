@@ -1,8 +1,11 @@
-use super::{config::Config, dir::process_dir, file::process_file};
+use super::{config::Config, dir::process_dir, file::process_file, tree::Tree};
 use anyhow::Result;
 use std::path::{Path, PathBuf};
 
-pub fn process(input: &Path, output: &Path, config: &Config) -> Result<()> {
+/// Puts the entries of the config into `tree` hiding private regions. Returns the problems
+/// that have anything hidden, i.e. should fail their tests.
+pub fn process(input: &Path, tree: &mut Tree, config: &Config) -> Result<Vec<PathBuf>> {
+    let mut stubbed = Vec::new();
     let to_process = std::iter::empty()
         .chain(
             config
@@ -19,12 +22,14 @@ pub fn process(input: &Path, output: &Path, config: &Config) -> Result<()> {
         .chain(config.get_copy().iter().cloned());
     for entry in to_process {
         let input = input.join(&entry);
-        let output = output.join(&entry);
-        if input.is_dir() {
-            process_dir(&input, &output)?;
+        let hidden = if input.is_dir() {
+            process_dir(&input, &entry, tree)?
         } else {
-            process_file(&input, &output)?;
+            process_file(&input, &entry, tree)?
+        };
+        if hidden && entry.starts_with("problems") {
+            stubbed.push(entry);
         }
     }
-    Ok(())
+    Ok(stubbed)
 }
@@ -1,9 +1,16 @@
-use super::{cargo_root::cargo_root, process::process, prune::prune, skip::skip};
-use crate::repository::repo::Repository;
+use super::{
+    cargo_root::cargo_root,
+    process::process,
+    skip::skip,
+    sync::sync,
+    tree::{Tree, TreeFile},
+    verify::{verify, Verification},
+};
+use crate::repository::repo::{Repository, REPOSITORY_MARKER};
 use anyhow::{Context, Result};
 use std::path::Path;
 
-pub fn run_compose(input: &Path, output: &Path) -> Result<()> {
+pub fn run_compose(input: &Path, output: &Path, verification: Verification) -> Result<()> {
     let repository = Repository::from_path(input)?;
     let config = repository.compose_config()?;
     let input = repository.get_path().to_path_buf();
@@ -17,8 +24,18 @@ pub fn run_compose(input: &Path, output: &Path) -> Result<()> {
                 .file_name()
                 .context("output path has no file name to canonicalize")?,
         );
-    prune(&output, &config)?;
-    process(&input, &output, &config)?;
-    cargo_root(&output, &config)?;
-    skip(&output, &config)
+    let mut tree = Tree::default();
+    let stubbed = process(&input, &mut tree, &config)?;
+    cargo_root(&input, &mut tree, &config)?;
+    skip(&mut tree, &config);
+    // The composed repository is found by rover whatever its name is.
+    tree.insert(
+        REPOSITORY_MARKER.into(),
+        TreeFile {
+            content: Vec::new(),
+            permissions: None,
+        },
+    );
+    sync(&output, &tree, &config)?.print();
+    verify(&output, &stubbed, verification)
 }
@@ -1,16 +1,7 @@
-use super::config::Config;
-use anyhow::{Context, Result};
-use std::{fs, path::Path};
+use super::{config::Config, tree::Tree};
 
-pub fn skip(output: &Path, config: &Config) -> Result<()> {
+pub fn skip(tree: &mut Tree, config: &Config) {
     for entry in config.get_skipped() {
-        let path = output.join(entry);
-        if path.is_dir() {
-            fs::remove_dir_all(&path)
-        } else {
-            fs::remove_file(&path)
-        }
-        .with_context(|| format!("failed to remove {path:?}"))?;
+        tree.remove(entry);
     }
-    Ok(())
 }
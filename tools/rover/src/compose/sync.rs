@@ -0,0 +1,275 @@
+use super::{config::Config, tree::Tree};
+use anyhow::{Context, Result};
+use std::{
+    collections::BTreeSet,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+/// Lists the files written by the previous compose, so the other files of the output
+/// are never touched.
+const MANIFEST: &str = ".compose-manifest";
+const MANIFEST_HEADER: &str = "# Files generated by rover compose, other files are kept intact.";
+
+pub enum Change {
+    Added,
+    Modified,
+    Removed,
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Self::Added => "added",
+            Self::Modified => "modified",
+            Self::Removed => "removed",
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct Changelog {
+    changes: Vec<(Change, PathBuf)>,
+    unchanged: usize,
+    /// Files of the output that may be left from a compose without a manifest.
+    unknown: Vec<PathBuf>,
+}
+
+impl Changelog {
+    pub fn print(&self) {
+        for (change, path) in &self.changes {
+            eprintln!("{change:8} {}", path.display());
+        }
+        let count = |kind: fn(&Change) -> bool| {
+            self.changes
+                .iter()
+                .filter(|(change, _)| kind(change))
+                .count()
+        };
+        eprintln!(
+            "{} added, {} modified, {} removed, {} unchanged",
+            count(|change| matches!(change, Change::Added)),
+            count(|change| matches!(change, Change::Modified)),
+            count(|change| matches!(change, Change::Removed)),
+            self.unchanged
+        );
+        if let Some(example) = self.unknown.first() {
+            eprintln!(
+                "warning: the output had no {MANIFEST}, so {} files not generated now, e.g. {}, are kept; if they are stale, compose into a clean directory once",
+                self.unknown.len(),
+                example.display()
+            );
+        }
+    }
+}
+
+/// Writes the files of the tree that differ from the ones in `output` and removes the files
+/// generated by the previous compose that are not in the tree anymore, unless they are
+/// protected by `do-not-delete`.
+pub fn sync(output: &Path, tree: &Tree, config: &Config) -> Result<Changelog> {
+    let protected = |path: &Path| {
+        config
+            .get_do_not_delete()
+            .iter()
+            .any(|entry| path.starts_with(entry))
+    };
+    let mut changelog = Changelog::default();
+    let mut generated = BTreeSet::new();
+    for (path, file) in tree.files() {
+        generated.insert(path.to_path_buf());
+        let dest = output.join(path);
+        let change = match fs::read(&dest) {
+            Err(_) => Change::Added,
+            Ok(content) if content != file.content => Change::Modified,
+            Ok(_) => {
+                let metadata = fs::metadata(&dest)
+                    .with_context(|| format!("failed to read metadata of {dest:?}"))?;
+                match &file.permissions {
+                    Some(permissions) if *permissions != metadata.permissions() => Change::Modified,
+                    _ => {
+                        changelog.unchanged += 1;
+                        continue;
+                    }
+                }
+            }
+        };
+        fs::create_dir_all(dest.parent().unwrap())
+            .with_context(|| format!("failed to create directory for {dest:?}"))?;
+        fs::write(&dest, &file.content).with_context(|| format!("failed to write {dest:?}"))?;
+        if let Some(permissions) = &file.permissions {
+            fs::set_permissions(&dest, permissions.clone())
+                .with_context(|| format!("failed to set permissions of {dest:?}"))?;
+        }
+        changelog.changes.push((change, path.to_path_buf()));
+    }
+    let manifest = read_manifest(output)?;
+    if manifest.is_none() && output.is_dir() {
+        find_unknown(
+            output,
+            output,
+            &generated,
+            &protected,
+            &mut changelog.unknown,
+        )?;
+    }
+    for path in manifest.unwrap_or_default() {
+        if generated.contains(&path) || protected(&path) {
+            continue;
+        }
+        let dest = output.join(&path);
+        if dest.is_file() {
+            fs::remove_file(&dest).with_context(|| format!("failed to remove {dest:?}"))?;
+            remove_empty_parents(output, &dest);
+            changelog.changes.push((Change::Removed, path));
+        }
+    }
+    write_manifest(output, &generated)?;
+    Ok(changelog)
+}
+
+/// Without a manifest nothing is known to be generated, so nothing is removed.
+fn read_manifest(output: &Path) -> Result<Option<BTreeSet<PathBuf>>> {
+    let path = output.join(MANIFEST);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let manifest = fs::read_to_string(&path).with_context(|| format!("failed to read {path:?}"))?;
+    Ok(Some(
+        manifest
+            .lines()
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(PathBuf::from)
+            .collect(),
+    ))
+}
+
+/// Collects the files of `dir` that are neither generated nor protected, `.git` aside.
+fn find_unknown(
+    output: &Path,
+    dir: &Path,
+    generated: &BTreeSet<PathBuf>,
+    protected: &impl Fn(&Path) -> bool,
+    unknown: &mut Vec<PathBuf>,
+) -> Result<()> {
+    let mut entries = fs::read_dir(dir)
+        .with_context(|| format!("failed to read {dir:?}"))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+    for entry in entries {
+        let path = entry.strip_prefix(output).unwrap();
+        if path == Path::new(".git") || path == Path::new(MANIFEST) || protected(path) {
+            continue;
+        }
+        let is_dir = fs::symlink_metadata(&entry)
+            .with_context(|| format!("failed to read metadata of {entry:?}"))?
+            .is_dir();
+        if is_dir {
+            find_unknown(output, &entry, generated, protected, unknown)?;
+        } else if !generated.contains(path) {
+            unknown.push(path.to_path_buf());
+        }
+    }
+    Ok(())
+}
+
+fn write_manifest(output: &Path, generated: &BTreeSet<PathBuf>) -> Result<()> {
+    let mut manifest = MANIFEST_HEADER.to_string() + "\n";
+    for path in generated {
+        manifest += path.to_str().context("non-utf-8 path")?;
+        manifest += "\n";
+    }
+    let path = output.join(MANIFEST);
+    fs::write(&path, manifest).with_context(|| format!("failed to write {path:?}"))
+}
+
+fn remove_empty_parents(output: &Path, file: &Path) {
+    for dir in file.ancestors().skip(1) {
+        if dir == output || fs::remove_dir(dir).is_err() {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compose::tree::TreeFile;
+    use std::{env, process};
+
+    fn tree(files: &[&str]) -> Tree {
+        let mut tree = Tree::default();
+        for file in files {
+            tree.insert(
+                file.into(),
+                TreeFile {
+                    content: file.as_bytes().to_vec(),
+                    permissions: None,
+                },
+            );
+        }
+        tree
+    }
+
+    fn changes(changelog: &Changelog) -> Vec<String> {
+        changelog
+            .changes
+            .iter()
+            .map(|(change, path)| format!("{change} {}", path.display()))
+            .collect()
+    }
+
+    #[test]
+    fn stale_files() {
+        let output = env::temp_dir().join(format!("rover-test-{}-sync", process::id()));
+        let _ = fs::remove_dir_all(&output);
+        fs::create_dir_all(output.join(".git")).unwrap();
+        fs::create_dir_all(output.join("problems/g/old")).unwrap();
+        fs::write(output.join(".git/HEAD"), "").unwrap();
+        fs::write(output.join("problems/g/old/lib.rs"), "").unwrap();
+        fs::write(output.join("notes.txt"), "").unwrap();
+        let config: Config = serde_yaml::from_str(
+            "problems: []\ntools: []\ncopy: []\nskip-entries: []\nadd-to-toml: []\ndo-not-delete: [notes.txt, docs]\n",
+        )
+        .unwrap();
+
+        // Without a manifest the files of an earlier compose are only reported.
+        let changelog = sync(
+            &output,
+            &tree(&["problems/g/a/lib.rs", "README.md", "docs/index.md"]),
+            &config,
+        )
+        .unwrap();
+        assert_eq!(
+            changes(&changelog),
+            [
+                "added README.md",
+                "added docs/index.md",
+                "added problems/g/a/lib.rs"
+            ]
+        );
+        assert_eq!(changelog.unknown, [PathBuf::from("problems/g/old/lib.rs")]);
+
+        let changelog = sync(
+            &output,
+            &tree(&["problems/g/b/lib.rs", "README.md"]),
+            &config,
+        )
+        .unwrap();
+        assert_eq!(
+            changes(&changelog),
+            ["added problems/g/b/lib.rs", "removed problems/g/a/lib.rs"]
+        );
+        assert!(changelog.unknown.is_empty());
+        assert!(!output.join("problems/g/a").exists());
+        assert!(output.join("problems/g/old/lib.rs").exists());
+        assert!(output.join("notes.txt").exists());
+        // Protected files are written when generated but never removed.
+        assert_eq!(
+            fs::read(output.join("docs/index.md")).unwrap(),
+            b"docs/index.md"
+        );
+
+        fs::remove_dir_all(&output).unwrap();
+    }
+}
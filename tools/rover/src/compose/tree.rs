@@ -0,0 +1,31 @@
+use std::{
+    collections::BTreeMap,
+    fs::Permissions,
+    path::{Path, PathBuf},
+};
+
+pub struct TreeFile {
+    pub content: Vec<u8>,
+    pub permissions: Option<Permissions>,
+}
+
+/// The composed repository in memory: the files by their paths relative to its root.
+#[derive(Default)]
+pub struct Tree {
+    files: BTreeMap<PathBuf, TreeFile>,
+}
+
+impl Tree {
+    pub fn insert(&mut self, path: PathBuf, file: TreeFile) {
+        self.files.insert(path, file);
+    }
+
+    /// Removes the file or the directory with everything inside.
+    pub fn remove(&mut self, path: &Path) {
+        self.files.retain(|file, _| !file.starts_with(path));
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = (&Path, &TreeFile)> {
+        self.files.iter().map(|(path, file)| (path.as_path(), file))
+    }
+}
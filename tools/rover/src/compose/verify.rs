@@ -0,0 +1,127 @@
+use crate::testing::{libtest::parse_tests, result::Outcome};
+use anyhow::{bail, Context, Result};
+use std::{
+    path::{Path, PathBuf},
+    process,
+};
+
+pub enum Verification {
+    None,
+    /// The composed workspace with its tests compiles.
+    Build,
+    /// Also the tests of the problems with hidden code fail or are ignored.
+    BuildAndTests,
+}
+
+pub fn verify(output: &Path, stubbed: &[PathBuf], verification: Verification) -> Result<()> {
+    if let Verification::None = verification {
+        return Ok(());
+    }
+    eprintln!("verifying that the composed repository compiles");
+    let status = process::Command::new("cargo")
+        .args(["check", "--workspace", "--tests"])
+        .current_dir(output)
+        .status()
+        .context("failed to launch cargo check")?;
+    if !status.success() {
+        bail!("composed repository does not compile, check the placement of private regions")
+    }
+    if let Verification::BuildAndTests = verification {
+        verify_tests_fail(output, stubbed)?;
+    }
+    Ok(())
+}
+
+fn verify_tests_fail(output: &Path, stubbed: &[PathBuf]) -> Result<()> {
+    let mut passed = Vec::new();
+    for problem in stubbed {
+        eprintln!("verifying that the tests of {} fail", problem.display());
+        let test = process::Command::new("cargo")
+            .args(["test", "--tests", "--no-fail-fast", "--manifest-path"])
+            .arg(problem.join("Cargo.toml"))
+            .current_dir(output)
+            .output()
+            .context("failed to launch cargo test")?;
+        passed.extend(
+            passed_tests(&test.stdout, &test.stderr)
+                .into_iter()
+                .map(|test| format!("{}: {test}", problem.display())),
+        );
+    }
+    if !passed.is_empty() {
+        bail!(
+            "tests pass without the hidden code:\n  {}",
+            passed.join("\n  ")
+        )
+    }
+    Ok(())
+}
+
+/// The passed tests in the output of `cargo test`, as `binary: name`. Failed and ignored
+/// tests are fine, as the hidden code is missing.
+fn passed_tests(stdout: &[u8], stderr: &[u8]) -> Vec<String> {
+    parse_tests(stdout, stderr)
+        .into_iter()
+        .filter(|test| test.outcome() == Outcome::Passed)
+        .map(|test| format!("{}: {}", test.binary(), test.name()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STDERR: &str = "   Compiling sum v0.1.0
+    Finished `test` profile [unoptimized + debuginfo] target(s) in 0.5s
+     Running unittests src/lib.rs (target/debug/deps/sum-0123)
+     Running tests/tests.rs (target/debug/deps/tests-4567)
+";
+
+    #[test]
+    fn all_tests_fail_or_are_ignored() {
+        let stdout = "
+running 0 tests
+
+test result: ok. 0 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out
+
+running 3 tests
+test add ... FAILED
+test slow ... ignored, too slow
+test sub ... FAILED
+
+failures:
+
+---- add stdout ----
+test nested ... ok
+thread 'add' panicked at src/lib.rs:2:5:
+not implemented
+
+failures:
+    add
+    sub
+
+test result: FAILED. 0 passed; 2 failed; 1 ignored; 0 measured; 0 filtered out
+";
+        assert!(passed_tests(stdout.as_bytes(), STDERR.as_bytes()).is_empty());
+    }
+
+    #[test]
+    fn some_tests_pass() {
+        let stdout = "
+running 1 test
+test tests::unit ... ok
+
+test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out
+
+running 2 tests
+test add ... FAILED
+test empty ... ok
+
+test result: FAILED. 1 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out
+";
+        assert_eq!(
+            passed_tests(stdout.as_bytes(), STDERR.as_bytes()),
+            ["src/lib.rs: tests::unit", "tests/tests.rs: empty"]
+        );
+    }
+}
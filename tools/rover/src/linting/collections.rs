@@ -0,0 +1,82 @@
+use super::{paths::collect_paths, source::SourceFile};
+use crate::testing::result::Violation;
+
+const COLLECTIONS_MODULES: [[&str; 2]; 2] = [["std", "collections"], ["alloc", "collections"]];
+const VEC_TYPES: [[&str; 3]; 2] = [["std", "vec", "Vec"], ["alloc", "vec", "Vec"]];
+const VEC_MACROS: [[&str; 2]; 2] = [["std", "vec"], ["alloc", "vec"]];
+
+/// Forbids anything from `std::collections`, `Vec` and `vec!`, including through aliases.
+pub fn check(source: &SourceFile) -> Vec<Violation> {
+    collect_paths(source.ast())
+        .into_iter()
+        .filter(|path| {
+            if path.is_macro {
+                VEC_MACROS.iter().any(|prefix| path.segments == prefix)
+            } else {
+                COLLECTIONS_MODULES
+                    .iter()
+                    .any(|prefix| path.starts_with(prefix))
+                    || VEC_TYPES.iter().any(|prefix| path.starts_with(prefix))
+            }
+        })
+        .map(|path| source.violation(path.span, format!("{} is forbidden", path.display())))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn violations(source: &str) -> Vec<String> {
+        let source = SourceFile::parse_str(source, Path::new("src/lib.rs"))
+            .ok()
+            .unwrap();
+        check(&source).iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn flags_collections_through_aliases() {
+        let violations = violations(
+            "use std::collections::HashMap as M;
+
+            fn f() -> M<u8, u8> {
+                M::new()
+            }",
+        );
+        assert_eq!(
+            violations,
+            [
+                "src/lib.rs:1: `std::collections::HashMap` is forbidden",
+                "src/lib.rs:3: `std::collections::HashMap` (as `M`) is forbidden",
+                "src/lib.rs:4: `std::collections::HashMap::new` (as `M::new`) is forbidden",
+            ]
+        );
+    }
+
+    #[test]
+    fn flags_vec_and_glob_imports() {
+        let violations = violations(
+            "use alloc::collections::*;
+            fn f() -> Vec<u8> { vec![] }",
+        );
+        assert_eq!(
+            violations,
+            [
+                "src/lib.rs:1: `alloc::collections` (as `alloc::collections::*`) is forbidden",
+                "src/lib.rs:2: `std::vec::Vec` (as `Vec`) is forbidden",
+                "src/lib.rs:2: `std::vec!` (as `vec!`) is forbidden",
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_similar_names_comments_and_strings() {
+        let violations = violations(
+            "struct VecLike;
+            // A Vec would do as well.
+            fn f() -> (VecLike, &'static str) { (VecLike, \"Vec<u8>\") }",
+        );
+        assert!(violations.is_empty(), "{violations:?}");
+    }
+}
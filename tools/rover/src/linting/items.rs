@@ -0,0 +1,170 @@
+use super::{paths::collect_paths, source::SourceFile};
+use crate::testing::result::Violation;
+use anyhow::{bail, Result};
+
+/// A banned path like `std::sync::mpsc`, `Rc::get_mut_unchecked`, crate name like `crossbeam`
+/// or macro like `println!`.
+#[derive(Clone, Debug)]
+pub struct ItemPattern {
+    segments: Vec<String>,
+    is_macro: bool,
+}
+
+impl ItemPattern {
+    pub fn parse(pattern: &str) -> Result<Self> {
+        let trimmed = pattern.trim().trim_start_matches("::");
+        let (path, is_macro) = match trimmed.strip_suffix('!') {
+            Some(path) => (path, true),
+            None => (trimmed, false),
+        };
+        let segments: Vec<_> = path.split("::").map(|s| s.trim().to_string()).collect();
+        for segment in &segments {
+            if syn::parse_str::<syn::Ident>(segment).is_err() {
+                bail!("forbidden item \"{pattern}\" is not a path")
+            }
+        }
+        Ok(Self { segments, is_macro })
+    }
+
+    /// Multi-segment patterns may match any part of the resolved path, single names
+    /// match only crates, and macros match the end of the path.
+    fn matches(&self, segments: &[String], is_macro: bool) -> bool {
+        if self.is_macro {
+            is_macro && segments.ends_with(&self.segments)
+        } else if self.segments.len() == 1 {
+            segments.first() == self.segments.first()
+        } else {
+            segments
+                .windows(self.segments.len())
+                .any(|window| window == self.segments.as_slice())
+        }
+    }
+}
+
+impl std::fmt::Display for ItemPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.segments.join("::"))?;
+        if self.is_macro {
+            write!(f, "!")?;
+        }
+        Ok(())
+    }
+}
+
+pub fn check(source: &SourceFile, patterns: &[ItemPattern]) -> Vec<Violation> {
+    collect_paths(source.ast())
+        .into_iter()
+        .filter_map(|path| {
+            let pattern = patterns
+                .iter()
+                .find(|pattern| pattern.matches(&path.segments, path.is_macro))?;
+            Some(source.violation(
+                path.span,
+                format!("{} is forbidden by `{pattern}`", path.display()),
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn violations(source: &str, patterns: &[&str]) -> Vec<String> {
+        let source = SourceFile::parse_str(source, Path::new("src/lib.rs"))
+            .ok()
+            .unwrap();
+        let patterns: Vec<_> = patterns
+            .iter()
+            .map(|pattern| ItemPattern::parse(pattern).unwrap())
+            .collect();
+        check(&source, &patterns)
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn parses_patterns() {
+        assert_eq!(
+            ItemPattern::parse("::std::sync::mpsc").unwrap().to_string(),
+            "std::sync::mpsc"
+        );
+        assert_eq!(
+            ItemPattern::parse("println!").unwrap().to_string(),
+            "println!"
+        );
+        assert!(ItemPattern::parse("std::sync::").is_err());
+        assert!(ItemPattern::parse("a b").is_err());
+    }
+
+    #[test]
+    fn forbids_crates() {
+        let violations = violations(
+            "extern crate crossbeam as cb;
+            use crossbeam::channel;
+            fn f() { cb::scope(|_| {}); }",
+            &["crossbeam"],
+        );
+        assert_eq!(
+            violations,
+            [
+                "src/lib.rs:1: `crossbeam` (as `extern crate crossbeam`) is forbidden by `crossbeam`",
+                "src/lib.rs:2: `crossbeam::channel` is forbidden by `crossbeam`",
+                "src/lib.rs:3: `crossbeam::scope` (as `cb::scope`) is forbidden by `crossbeam`",
+            ]
+        );
+    }
+
+    #[test]
+    fn forbids_path_prefixes_through_aliases() {
+        let violations = violations(
+            "use std::sync as s;
+            fn f() { s::mpsc::channel::<u8>(); s::Arc::new(1); }",
+            &["std::sync::mpsc"],
+        );
+        assert_eq!(
+            violations,
+            ["src/lib.rs:2: `std::sync::mpsc::channel` (as `s::mpsc::channel`) is forbidden by `std::sync::mpsc`"]
+        );
+    }
+
+    #[test]
+    fn forbids_associated_items() {
+        let violations = violations(
+            "use std::rc::Rc;
+            fn f(mut rc: Rc<u8>) { unsafe { Rc::get_mut_unchecked(&mut rc) }; Rc::get_mut(&mut rc); }",
+            &["Rc::get_mut_unchecked"],
+        );
+        assert_eq!(
+            violations,
+            ["src/lib.rs:2: `std::rc::Rc::get_mut_unchecked` (as `Rc::get_mut_unchecked`) is forbidden by `Rc::get_mut_unchecked`"]
+        );
+    }
+
+    #[test]
+    fn forbids_macros() {
+        let violations = violations(
+            "fn f() { println!(\"{}\", 1); std::println!(); eprintln!(); }",
+            &["println!"],
+        );
+        assert_eq!(
+            violations,
+            [
+                "src/lib.rs:1: `std::println!` (as `println!`) is forbidden by `println!`",
+                "src/lib.rs:1: `std::println!` is forbidden by `println!`",
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_local_items() {
+        let violations = violations(
+            "mod crossbeam { pub fn scope() {} }
+            fn f() { crossbeam::scope(); }",
+            &["crossbeam"],
+        );
+        assert!(violations.is_empty(), "{violations:?}");
+    }
+}
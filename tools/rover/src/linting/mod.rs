@@ -0,0 +1,32 @@
+pub mod collections;
+pub mod items;
+pub mod no_std;
+mod paths;
+mod source;
+pub mod unsafe_code;
+
+use crate::{
+    repository::context::CommandContext,
+    testing::result::{CommandResult, Violation},
+};
+use anyhow::Result;
+use source::SourceFile;
+
+/// Parses every user file and reports violations of the lint.
+pub fn lint_user_files(
+    context: &CommandContext,
+    result: &mut CommandResult,
+    lint: impl Fn(&SourceFile) -> Vec<Violation>,
+) -> Result<()> {
+    for file in context.get_user_files() {
+        let path = file.strip_prefix(context.get_workdir()).unwrap_or(file);
+        let violations = match SourceFile::parse(file, path)? {
+            Ok(source) => lint(&source),
+            Err(violation) => vec![violation],
+        };
+        for violation in violations {
+            result.add_violation(violation);
+        }
+    }
+    Ok(())
+}
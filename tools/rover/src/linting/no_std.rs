@@ -0,0 +1,49 @@
+use super::source::SourceFile;
+use crate::testing::result::Violation;
+
+/// Requires `#![no_std]` and forbids bringing `std` back with `extern crate`.
+pub fn check(source: &SourceFile) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    if !source.has_inner_attribute("no_std", None) {
+        violations.push(
+            source.file_violation("file does not contain attribute `#![no_std]`".to_string()),
+        );
+    }
+    for item in &source.ast().items {
+        if let syn::Item::ExternCrate(item) = item {
+            if item.ident == "std" {
+                violations.push(source.violation(
+                    item.ident.span(),
+                    "`extern crate std` is forbidden".to_string(),
+                ));
+            }
+        }
+    }
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn violations(source: &str) -> Vec<String> {
+        let source = SourceFile::parse_str(source, Path::new("src/lib.rs"))
+            .ok()
+            .unwrap();
+        check(&source).iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn requires_no_std() {
+        assert!(violations("#![no_std]\nextern crate alloc;").is_empty());
+        assert_eq!(
+            violations("#[no_std] fn f() {}"),
+            ["src/lib.rs: file does not contain attribute `#![no_std]`"]
+        );
+        assert_eq!(
+            violations("#![no_std]\nextern crate std;"),
+            ["src/lib.rs:2: `extern crate std` is forbidden"]
+        );
+    }
+}
@@ -0,0 +1,310 @@
+use proc_macro2::Span;
+use std::collections::{HashMap, HashSet};
+use syn::{
+    punctuated::Punctuated,
+    visit::{self, Visit},
+    Expr, Token, UseTree,
+};
+
+/// Names from the standard prelude that may be used without an import.
+const PRELUDE: [(&str, &str); 16] = [
+    ("Box", "std::boxed::Box"),
+    ("String", "std::string::String"),
+    ("ToString", "std::string::ToString"),
+    ("Vec", "std::vec::Vec"),
+    ("Option", "std::option::Option"),
+    ("Some", "std::option::Option::Some"),
+    ("None", "std::option::Option::None"),
+    ("Result", "std::result::Result"),
+    ("Ok", "std::result::Result::Ok"),
+    ("Err", "std::result::Result::Err"),
+    ("Clone", "std::clone::Clone"),
+    ("Default", "std::default::Default"),
+    ("Drop", "std::ops::Drop"),
+    ("Iterator", "std::iter::Iterator"),
+    ("IntoIterator", "std::iter::IntoIterator"),
+    ("ToOwned", "std::borrow::ToOwned"),
+];
+
+/// A path met in the source, resolved through the imports of the file as far as possible.
+pub struct UsedPath {
+    pub segments: Vec<String>,
+    pub written: String,
+    pub is_macro: bool,
+    pub span: Span,
+}
+
+impl UsedPath {
+    pub fn starts_with(&self, prefix: &[&str]) -> bool {
+        self.segments.len() >= prefix.len() && self.segments.iter().zip(prefix).all(|(a, b)| a == b)
+    }
+
+    pub fn display(&self) -> String {
+        let resolved = self.segments.join("::") + if self.is_macro { "!" } else { "" };
+        if resolved == self.written {
+            format!("`{resolved}`")
+        } else {
+            format!("`{resolved}` (as `{}`)", self.written)
+        }
+    }
+}
+
+/// Collects every path of the file: imports, types, expressions, patterns and macros.
+/// The bodies of macros are inspected if they parse as a list of expressions.
+pub fn collect_paths(file: &syn::File) -> Vec<UsedPath> {
+    let mut imports = Imports::default();
+    imports.visit_file(file);
+    let mut collector = PathCollector {
+        imports,
+        paths: Vec::new(),
+    };
+    collector.visit_file(file);
+    collector.paths
+}
+
+#[derive(Default)]
+struct Imports {
+    aliases: HashMap<String, Vec<String>>,
+    locals: HashSet<String>,
+}
+
+impl Imports {
+    fn resolve(&self, path: &syn::Path, is_macro: bool) -> Vec<String> {
+        let segments: Vec<_> = path
+            .segments
+            .iter()
+            .map(|segment| segment.ident.to_string())
+            .collect();
+        if path.leading_colon.is_some() {
+            return segments;
+        }
+        // Items of the file itself can't be confused with crates or the prelude.
+        if self.locals.contains(&segments[0]) {
+            return ["crate".to_string()].into_iter().chain(segments).collect();
+        }
+        if let Some(full) = self.aliases.get(&segments[0]) {
+            return full.iter().chain(&segments[1..]).cloned().collect();
+        }
+        if is_macro && segments.len() == 1 {
+            return vec!["std".to_string(), segments[0].clone()];
+        }
+        if let Some((_, full)) = PRELUDE.iter().find(|(name, _)| *name == segments[0]) {
+            return full
+                .split("::")
+                .map(str::to_string)
+                .chain(segments[1..].iter().cloned())
+                .collect();
+        }
+        segments
+    }
+}
+
+impl<'ast> Visit<'ast> for Imports {
+    fn visit_item_use(&mut self, item: &'ast syn::ItemUse) {
+        for leaf in flatten_use_tree(&item.tree, Vec::new()) {
+            if let Some(binding) = leaf.binding {
+                self.aliases.insert(binding, leaf.segments);
+            }
+        }
+    }
+
+    fn visit_item_extern_crate(&mut self, item: &'ast syn::ItemExternCrate) {
+        let name = item.ident.to_string();
+        let binding = match &item.rename {
+            Some((_, rename)) => rename.to_string(),
+            None => name.clone(),
+        };
+        self.aliases.insert(binding, vec![name]);
+    }
+
+    fn visit_item(&mut self, item: &'ast syn::Item) {
+        let ident = match item {
+            syn::Item::Struct(item) => Some(&item.ident),
+            syn::Item::Enum(item) => Some(&item.ident),
+            syn::Item::Union(item) => Some(&item.ident),
+            syn::Item::Type(item) => Some(&item.ident),
+            syn::Item::Trait(item) => Some(&item.ident),
+            syn::Item::Fn(item) => Some(&item.sig.ident),
+            syn::Item::Mod(item) => Some(&item.ident),
+            syn::Item::Const(item) => Some(&item.ident),
+            syn::Item::Static(item) => Some(&item.ident),
+            syn::Item::Macro(item) => item.ident.as_ref(),
+            _ => None,
+        };
+        if let Some(ident) = ident {
+            self.locals.insert(ident.to_string());
+        }
+        visit::visit_item(self, item);
+    }
+}
+
+struct UseLeaf {
+    segments: Vec<String>,
+    binding: Option<String>,
+    is_glob: bool,
+    span: Span,
+}
+
+fn flatten_use_tree(tree: &UseTree, prefix: Vec<String>) -> Vec<UseLeaf> {
+    let leaf = |ident: &syn::Ident, binding: Option<&syn::Ident>| {
+        let mut segments = prefix.clone();
+        if ident != "self" {
+            segments.push(ident.to_string());
+        }
+        let binding = match binding {
+            Some(rename) => Some(rename.to_string()),
+            None if ident == "self" => segments.last().cloned(),
+            None => Some(ident.to_string()),
+        };
+        UseLeaf {
+            segments,
+            binding: binding.filter(|binding| binding != "_"),
+            is_glob: false,
+            span: ident.span(),
+        }
+    };
+    match tree {
+        UseTree::Path(path) => {
+            let mut prefix = prefix.clone();
+            prefix.push(path.ident.to_string());
+            flatten_use_tree(&path.tree, prefix)
+        }
+        UseTree::Name(name) => vec![leaf(&name.ident, None)],
+        UseTree::Rename(rename) => vec![leaf(&rename.ident, Some(&rename.rename))],
+        UseTree::Glob(glob) => vec![UseLeaf {
+            segments: prefix,
+            binding: None,
+            is_glob: true,
+            span: glob.star_token.span,
+        }],
+        UseTree::Group(group) => group
+            .items
+            .iter()
+            .flat_map(|tree| flatten_use_tree(tree, prefix.clone()))
+            .collect(),
+    }
+}
+
+struct PathCollector {
+    imports: Imports,
+    paths: Vec<UsedPath>,
+}
+
+impl PathCollector {
+    fn push(&mut self, path: &syn::Path, is_macro: bool) {
+        let written = path
+            .segments
+            .iter()
+            .map(|segment| segment.ident.to_string())
+            .collect::<Vec<_>>()
+            .join("::");
+        self.paths.push(UsedPath {
+            segments: self.imports.resolve(path, is_macro),
+            written: written + if is_macro { "!" } else { "" },
+            is_macro,
+            span: path.segments[0].ident.span(),
+        });
+    }
+}
+
+impl<'ast> Visit<'ast> for PathCollector {
+    fn visit_item_use(&mut self, item: &'ast syn::ItemUse) {
+        for leaf in flatten_use_tree(&item.tree, Vec::new()) {
+            let written = leaf.segments.join("::") + if leaf.is_glob { "::*" } else { "" };
+            self.paths.push(UsedPath {
+                segments: leaf.segments,
+                written,
+                is_macro: false,
+                span: leaf.span,
+            });
+        }
+    }
+
+    fn visit_item_extern_crate(&mut self, item: &'ast syn::ItemExternCrate) {
+        self.paths.push(UsedPath {
+            segments: vec![item.ident.to_string()],
+            written: format!("extern crate {}", item.ident),
+            is_macro: false,
+            span: item.ident.span(),
+        });
+    }
+
+    fn visit_attribute(&mut self, _: &'ast syn::Attribute) {}
+
+    fn visit_path(&mut self, path: &'ast syn::Path) {
+        self.push(path, false);
+        visit::visit_path(self, path);
+    }
+
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        self.push(&mac.path, true);
+        if let Ok(exprs) = mac.parse_body_with(Punctuated::<Expr, Token![,]>::parse_terminated) {
+            for expr in &exprs {
+                self.visit_expr(expr);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolved(source: &str) -> Vec<(String, bool)> {
+        collect_paths(&syn::parse_file(source).unwrap())
+            .into_iter()
+            .map(|path| (path.segments.join("::"), path.is_macro))
+            .collect()
+    }
+
+    fn contains(paths: &[(String, bool)], path: &str, is_macro: bool) -> bool {
+        paths.contains(&(path.to_string(), is_macro))
+    }
+
+    #[test]
+    fn resolves_aliases() {
+        let paths = resolved(
+            "use std::collections::HashMap as M;
+            use std::sync;
+            fn f() { let _: M<u8, u8> = M::new(); sync::mpsc::channel::<u8>(); }",
+        );
+        assert!(contains(&paths, "std::collections::HashMap", false));
+        assert!(contains(&paths, "std::collections::HashMap::new", false));
+        assert!(contains(&paths, "std::sync::mpsc::channel", false));
+    }
+
+    #[test]
+    fn resolves_prelude_and_macros() {
+        let paths = resolved("fn f() -> Vec<u8> { println!(\"{:?}\", vec![Some(1)]); vec![] }");
+        assert!(contains(&paths, "std::vec::Vec", false));
+        assert!(contains(&paths, "std::vec", true));
+        assert!(contains(&paths, "std::println", true));
+        // The bodies of macros are inspected as well.
+        assert!(contains(&paths, "std::option::Option::Some", false));
+    }
+
+    #[test]
+    fn keeps_glob_imports() {
+        let paths = resolved("use std::collections::*; use alloc::{vec::*, string::String};");
+        assert!(contains(&paths, "std::collections", false));
+        assert!(contains(&paths, "alloc::vec", false));
+        assert!(contains(&paths, "alloc::string::String", false));
+    }
+
+    #[test]
+    fn locals_shadow_prelude() {
+        let paths = resolved("struct Vec; fn f() -> Vec { Vec }");
+        assert!(contains(&paths, "crate::Vec", false));
+        assert!(!contains(&paths, "std::vec::Vec", false));
+    }
+
+    #[test]
+    fn skips_comments_strings_and_attributes() {
+        let paths = resolved(
+            "/// Uses a Vec.
+            #[doc = \"Vec\"]
+            fn f() -> &'static str { \"Vec\" }",
+        );
+        assert!(paths.iter().all(|(path, _)| !path.contains("Vec")));
+    }
+}
@@ -0,0 +1,70 @@
+use crate::testing::result::Violation;
+use anyhow::{Context, Result};
+use proc_macro2::Span;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+pub struct SourceFile {
+    path: PathBuf,
+    ast: syn::File,
+}
+
+impl SourceFile {
+    /// Parses the file, `path` is only used in violations. A file that is not valid Rust
+    /// is reported as a violation.
+    pub fn parse(file: &Path, path: &Path) -> Result<std::result::Result<Self, Violation>> {
+        let content =
+            fs::read_to_string(file).with_context(|| format!("failed to read file {file:?}"))?;
+        Ok(Self::parse_str(&content, path))
+    }
+
+    pub fn parse_str(content: &str, path: &Path) -> std::result::Result<Self, Violation> {
+        match syn::parse_file(content) {
+            Ok(ast) => Ok(Self {
+                path: path.to_path_buf(),
+                ast,
+            }),
+            Err(err) => Err(Violation::new(
+                path,
+                Some(line(err.span())),
+                format!("failed to parse file: {err}"),
+            )),
+        }
+    }
+
+    pub fn ast(&self) -> &syn::File {
+        &self.ast
+    }
+
+    pub fn violation(&self, span: Span, message: String) -> Violation {
+        Violation::new(&self.path, Some(line(span)), message)
+    }
+
+    pub fn file_violation(&self, message: String) -> Violation {
+        Violation::new(&self.path, None, message)
+    }
+
+    /// Checks whether the file has an inner attribute like `#![name(arg)]` or `#![name]`.
+    pub fn has_inner_attribute(&self, name: &str, arg: Option<&str>) -> bool {
+        self.ast
+            .attrs
+            .iter()
+            .filter(|attr| matches!(attr.style, syn::AttrStyle::Inner(_)))
+            .filter(|attr| attr.path().is_ident(name))
+            .any(|attr| match arg {
+                None => matches!(attr.meta, syn::Meta::Path(_)),
+                Some(arg) => attr
+                    .parse_args_with(
+                        syn::punctuated::Punctuated::<syn::Path, syn::Token![,]>::parse_terminated,
+                    )
+                    .map(|args| args.iter().any(|path| path.is_ident(arg)))
+                    .unwrap_or(false),
+            })
+    }
+}
+
+pub fn line(span: Span) -> usize {
+    span.start().line
+}
@@ -0,0 +1,123 @@
+use super::source::SourceFile;
+use crate::testing::result::Violation;
+use proc_macro2::Span;
+use syn::{
+    punctuated::Punctuated,
+    spanned::Spanned,
+    visit::{self, Visit},
+    Expr, Token,
+};
+
+/// Requires `#![forbid(unsafe_code)]` and reports every `unsafe` in the file.
+pub fn check(source: &SourceFile) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    if !source.has_inner_attribute("forbid", Some("unsafe_code")) {
+        violations.push(source.file_violation(
+            "file does not contain attribute `#![forbid(unsafe_code)]`".to_string(),
+        ));
+    }
+    let mut visitor = UnsafeVisitor {
+        source,
+        violations: &mut violations,
+    };
+    visitor.visit_file(source.ast());
+    violations
+}
+
+struct UnsafeVisitor<'a> {
+    source: &'a SourceFile,
+    violations: &'a mut Vec<Violation>,
+}
+
+impl UnsafeVisitor<'_> {
+    fn report(&mut self, unsafety: Option<&Token![unsafe]>, what: &str) {
+        if let Some(unsafety) = unsafety {
+            self.report_span(unsafety.span, what);
+        }
+    }
+
+    fn report_span(&mut self, span: Span, what: &str) {
+        self.violations
+            .push(self.source.violation(span, format!("{what} is forbidden")));
+    }
+}
+
+impl<'ast> Visit<'ast> for UnsafeVisitor<'_> {
+    fn visit_expr_unsafe(&mut self, expr: &'ast syn::ExprUnsafe) {
+        self.report_span(expr.unsafe_token.span, "unsafe block");
+        visit::visit_expr_unsafe(self, expr);
+    }
+
+    fn visit_signature(&mut self, sig: &'ast syn::Signature) {
+        self.report(sig.unsafety.as_ref(), "unsafe function");
+        visit::visit_signature(self, sig);
+    }
+
+    fn visit_item_impl(&mut self, item: &'ast syn::ItemImpl) {
+        self.report(item.unsafety.as_ref(), "unsafe impl");
+        visit::visit_item_impl(self, item);
+    }
+
+    fn visit_item_trait(&mut self, item: &'ast syn::ItemTrait) {
+        self.report(item.unsafety.as_ref(), "unsafe trait");
+        visit::visit_item_trait(self, item);
+    }
+
+    fn visit_item_foreign_mod(&mut self, item: &'ast syn::ItemForeignMod) {
+        self.report_span(item.abi.span(), "extern block");
+        visit::visit_item_foreign_mod(self, item);
+    }
+
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        if let Ok(exprs) = mac.parse_body_with(Punctuated::<Expr, Token![,]>::parse_terminated) {
+            for expr in &exprs {
+                self.visit_expr(expr);
+            }
+        }
+        visit::visit_macro(self, mac);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn violations(source: &str) -> Vec<String> {
+        let source = SourceFile::parse_str(source, Path::new("src/lib.rs"))
+            .ok()
+            .unwrap();
+        check(&source).iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn flags_unsafe_code() {
+        let violations = violations(
+            "#![forbid(unsafe_code)]
+            struct S;
+            unsafe impl Send for S {}
+            fn f() {
+                unsafe { g() };
+                println!(\"{}\", unsafe { g() });
+            }
+            unsafe fn g() {}",
+        );
+        assert_eq!(
+            violations,
+            [
+                "src/lib.rs:3: unsafe impl is forbidden",
+                "src/lib.rs:5: unsafe block is forbidden",
+                "src/lib.rs:6: unsafe block is forbidden",
+                "src/lib.rs:8: unsafe function is forbidden",
+            ]
+        );
+    }
+
+    #[test]
+    fn requires_inner_attribute() {
+        assert!(violations("#![forbid(missing_docs, unsafe_code)]").is_empty());
+        let expected = ["src/lib.rs: file does not contain attribute `#![forbid(unsafe_code)]`"];
+        assert_eq!(violations("#[forbid(unsafe_code)] fn f() {}"), expected);
+        assert_eq!(violations("#![deny(unsafe_code)]"), expected);
+    }
+}
@@ -0,0 +1,37 @@
+use crate::{repository::repo::Repository, util::table::format_table};
+use anyhow::Result;
+use std::path::Path;
+
+/// Prints every problem of the repository with its toolchain, steps and allowed patterns.
+pub fn list_problems(path: &Path) -> Result<()> {
+    let repository = Repository::from_path(path)?;
+    let mut rows = Vec::new();
+    for problem in repository.problems()? {
+        let row = match problem.config() {
+            Ok(config) => vec![
+                problem.branch_name(),
+                config.get_toolchain().name().to_string(),
+                config
+                    .get_steps()
+                    .iter()
+                    .map(|step| step.name())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                config.get_patterns().join(", "),
+            ],
+            Err(err) => {
+                eprintln!("{}: {err:#}", problem.branch_name());
+                vec![problem.branch_name(), "invalid config".to_string()]
+            }
+        };
+        rows.push(row);
+    }
+    print!(
+        "{}",
+        format_table(
+            &["PROBLEM", "TOOLCHAIN", "STEPS", "ALLOWED PATTERNS"],
+            &rows
+        )
+    );
+    Ok(())
+}
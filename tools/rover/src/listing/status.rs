@@ -0,0 +1,71 @@
+use crate::{
+    repository::{problem::Problem, repo::Repository},
+    testing::history::LastRun,
+    util::table::format_table,
+};
+use anyhow::Result;
+use std::{path::Path, time::Duration};
+
+/// Prints for every problem whether its user files were changed since the template,
+/// whether they have uncommitted changes and the outcome of the last local testing.
+pub fn print_status(path: &Path) -> Result<()> {
+    let repository = Repository::from_path(path)?;
+    let rows: Vec<_> = repository
+        .problems()?
+        .iter()
+        .map(|problem| {
+            vec![
+                problem.branch_name(),
+                files_status(problem),
+                git_status(problem),
+                last_run_status(problem),
+            ]
+        })
+        .collect();
+    print!(
+        "{}",
+        format_table(&["PROBLEM", "FILES", "GIT STATUS", "LAST TEST"], &rows)
+    );
+    Ok(())
+}
+
+fn files_status(problem: &Problem) -> String {
+    match problem.has_changes() {
+        Ok(true) => "modified".to_string(),
+        Ok(false) => "untouched".to_string(),
+        Err(err) => {
+            eprintln!("{}: {err:#}", problem.branch_name());
+            "unknown".to_string()
+        }
+    }
+}
+
+fn git_status(problem: &Problem) -> String {
+    match problem.has_uncommitted_changes() {
+        Ok(true) => "uncommitted".to_string(),
+        Ok(false) => "clean".to_string(),
+        Err(err) => {
+            eprintln!("{}: {err:#}", problem.branch_name());
+            "unknown".to_string()
+        }
+    }
+}
+
+fn last_run_status(problem: &Problem) -> String {
+    match LastRun::load(problem) {
+        Ok(Some(last_run)) if last_run.elapsed() < Duration::from_secs(60) => {
+            format!("{} just now", last_run.outcome())
+        }
+        Ok(Some(last_run)) => {
+            // Only the largest unit, like "3h" or "2days".
+            let elapsed = humantime::format_duration(last_run.elapsed()).to_string();
+            let elapsed = elapsed.split(' ').next().unwrap();
+            format!("{} {elapsed} ago", last_run.outcome())
+        }
+        Ok(None) => "never".to_string(),
+        Err(err) => {
+            eprintln!("{}: {err:#}", problem.branch_name());
+            "unknown".to_string()
+        }
+    }
+}
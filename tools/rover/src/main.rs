@@ -1,20 +1,42 @@
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
+use checking::check_config::check_config;
 use clap::{Arg, Command};
-use compose::run_compose::run_compose;
-use repository::repo::Repository;
-use std::path::PathBuf;
+use compose::{run_compose::run_compose, verify::Verification};
+use listing::{list::list_problems, status::print_status};
+use repository::{repo::Repository, sandbox::Sandbox};
+use scaffolding::new_problem::{new_problem, Template};
+use std::{num::NonZeroUsize, path::PathBuf, thread};
 use submitting::submit::submit_problem;
-use testing::{report::ReportType, test::test_problem};
+use testing::{
+    output::OutputFormat,
+    report::{reporter_from_name, ReportConfig},
+    test::{report_results, test_all_problems, test_problem},
+    watch::watch_problem,
+};
 
+mod checking;
 mod compose;
+mod linting;
+mod listing;
 mod repository;
+mod scaffolding;
 mod submitting;
 mod testing;
 mod util;
 
 fn main() -> Result<()> {
+    util::process_group::install_interrupt_handler()?;
+    repository::overlay::restore_leftover_overlays()?;
     let matches = Command::new("rover")
         .about("Helper tool for the Rust language course")
+        .arg(
+            Arg::new("repository")
+                .long("repository")
+                .help("Root of the course repository instead of the detected one, also set by ROVER_REPOSITORY")
+                .required(false)
+                .global(true)
+                .takes_value(true)
+        )
         .subcommand(
             Command::new("submit")
                 .about("Submit the results to the CI")
@@ -42,6 +64,13 @@ fn main() -> Result<()> {
                         .required(false)
                         .takes_value(true)
                 )
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .help("Show the files to submit and their diff against the branch without changing anything")
+                        .required(false)
+                        .takes_value(false)
+                )
         )
         .subcommand(
             Command::new("test")
@@ -61,6 +90,7 @@ fn main() -> Result<()> {
                         .help("Path to the repository with solutions to move files from")
                         .required(false)
                         .requires("path")
+                        .conflicts_with("all")
                         .takes_value(true)
                 )
                 .arg(
@@ -71,6 +101,75 @@ fn main() -> Result<()> {
                         .requires("move-files")
                         .takes_value(false)
                 )
+                .arg(
+                    Arg::new("output-format")
+                        .long("output-format")
+                        .help("Format of the testing results: \"text\", \"json\" or \"junit\"")
+                        .required(false)
+                        .default_value("text")
+                        .takes_value(true)
+                )
+                .arg(
+                    Arg::new("keep-going")
+                        .long("keep-going")
+                        .help("Run all the steps even if some of them fail")
+                        .required(false)
+                        .takes_value(false)
+                )
+                .arg(
+                    Arg::new("all")
+                        .long("all")
+                        .help("Test all the problems of the repository in parallel")
+                        .required(false)
+                        .takes_value(false)
+                )
+                .arg(
+                    Arg::new("group")
+                        .long("group")
+                        .help("Test only the problems of the group, used with \"--all\"")
+                        .required(false)
+                        .requires("all")
+                        .takes_value(true)
+                )
+                .arg(
+                    Arg::new("jobs")
+                        .long("jobs")
+                        .help("How many problems are tested at once with \"--all\", the number of CPUs by default")
+                        .required(false)
+                        .requires("all")
+                        .takes_value(true)
+                )
+                .arg(
+                    Arg::new("watch")
+                        .long("watch")
+                        .help("Test the problem again on every change of its user files")
+                        .required(false)
+                        .conflicts_with("all")
+                        .takes_value(false)
+                )
+                .arg(
+                    Arg::new("sandbox")
+                        .long("sandbox")
+                        .help("Launch the commands in a sandbox: read-only repository, no network, limited memory and processes")
+                        .required(false)
+                        .takes_value(false)
+                )
+                .arg(
+                    Arg::new("sandbox-memory")
+                        .long("sandbox-memory")
+                        .help("Address space limit of every process in the sandbox in MiB, 4096 by default")
+                        .required(false)
+                        .requires("sandbox")
+                        .takes_value(true)
+                )
+                .arg(
+                    Arg::new("sandbox-pids")
+                        .long("sandbox-pids")
+                        .help("Limit on the number of processes of the user, including the ones outside of the sandbox, 512 by default")
+                        .required(false)
+                        .requires("sandbox")
+                        .takes_value(true)
+                )
                 .arg(
                     Arg::new("report-to")
                         .long("report-to")
@@ -79,6 +178,13 @@ fn main() -> Result<()> {
                         .default_value("no-report")
                         .takes_value(true)
                 )
+                .arg(
+                    Arg::new("report-config")
+                        .long("report-config")
+                        .help("Path to the yml file with the settings of report systems")
+                        .required(false)
+                        .takes_value(true)
+                )
         )
         .subcommand(
             Command::new("compose")
@@ -98,36 +204,203 @@ fn main() -> Result<()> {
                         .required(true)
                         .takes_value(true)
                 )
+                .arg(
+                    Arg::new("verify")
+                        .long("verify")
+                        .help("Check that the composed repository compiles with its tests")
+                        .required(false)
+                        .takes_value(false)
+                )
+                .arg(
+                    Arg::new("verify-tests")
+                        .long("verify-tests")
+                        .help("Also check that the tests of problems with hidden code fail or are ignored")
+                        .required(false)
+                        .takes_value(false)
+                )
+        )
+        .subcommand(
+            Command::new("check-config")
+                .about("Validate \".config.yml\" of the problem")
+                .arg(
+                    Arg::new("path")
+                        .long("path")
+                        .help("Path to the problem within course repository")
+                        .required(false)
+                        .default_value(".")
+                        .hide_default_value(true)
+                        .takes_value(true)
+                )
+                .arg(
+                    Arg::new("all")
+                        .long("all")
+                        .help("Validate configs of all the problems in the repository")
+                        .required(false)
+                        .takes_value(false)
+                )
+        )
+        .subcommand(
+            Command::new("list")
+                .about("List the problems of the course repository")
+                .arg(
+                    Arg::new("path")
+                        .long("path")
+                        .help("Path within course repository")
+                        .required(false)
+                        .default_value(".")
+                        .hide_default_value(true)
+                        .takes_value(true)
+                )
+        )
+        .subcommand(
+            Command::new("status")
+                .about("Show which problems are changed and their last local testing results")
+                .arg(
+                    Arg::new("path")
+                        .long("path")
+                        .help("Path within course repository")
+                        .required(false)
+                        .default_value(".")
+                        .hide_default_value(true)
+                        .takes_value(true)
+                )
+        )
+        .subcommand(
+            Command::new("new-problem")
+                .about("Create a problem from a template and register it in the repository")
+                .arg(
+                    Arg::new("group")
+                        .help("Group of the problem")
+                        .required(true)
+                        .index(1)
+                )
+                .arg(
+                    Arg::new("title")
+                        .help("Title of the problem")
+                        .required(true)
+                        .index(2)
+                )
+                .arg(
+                    Arg::new("template")
+                        .long("template")
+                        .help("Kind of the crate: \"lib\", \"bin\" or \"proc-macro\"")
+                        .required(false)
+                        .default_value("lib")
+                        .takes_value(true)
+                )
+                .arg(
+                    Arg::new("path")
+                        .long("path")
+                        .help("Path within course repository")
+                        .required(false)
+                        .default_value(".")
+                        .hide_default_value(true)
+                        .takes_value(true)
+                )
         )
         .arg_required_else_help(true)
         .get_matches();
+    if let Some(root) = matches.value_of("repository") {
+        Repository::override_root(root.into());
+    }
 
     match matches.subcommand() {
         Some(("submit", submit_matches)) => {
             let path: PathBuf = submit_matches.value_of("path").unwrap().into();
             let message = submit_matches.value_of("message").unwrap();
             let solutions_repo = submit_matches.value_of("solutions-repo").map(PathBuf::from);
-            submit_problem(&path, message, solutions_repo)
+            let dry_run = submit_matches.is_present("dry-run");
+            submit_problem(&path, message, solutions_repo, dry_run)
         }
         Some(("test", test_matches)) => {
             let path: PathBuf = test_matches.value_of("path").unwrap().into();
             let repository = Repository::from_path(&path)?;
+            let report_config = match test_matches.value_of("report-config") {
+                Some(path) => ReportConfig::from_yml(path.as_ref())?,
+                None => ReportConfig::default(),
+            };
+            let reporter =
+                reporter_from_name(test_matches.value_of("report-to").unwrap(), report_config)?;
+            let output_format =
+                OutputFormat::from_name(test_matches.value_of("output-format").unwrap())?;
+            if reporter.writes_to_stdout() && output_format.owns_stdout() {
+                bail!(
+                    "both the report and --output-format {} are printed to stdout, set \"path\" of the \"file\" report",
+                    test_matches.value_of("output-format").unwrap()
+                )
+            }
+            let keep_going = test_matches.is_present("keep-going");
+            let sandbox = if test_matches.is_present("sandbox") {
+                let memory = match test_matches.value_of("sandbox-memory") {
+                    Some(memory) => memory.parse().context("invalid sandbox memory limit")?,
+                    None => Sandbox::DEFAULT_MEMORY_LIMIT_MB,
+                };
+                let pids = match test_matches.value_of("sandbox-pids") {
+                    Some(pids) => pids.parse().context("invalid sandbox process limit")?,
+                    None => Sandbox::DEFAULT_PIDS_LIMIT,
+                };
+                Some(Sandbox::new(repository.get_path(), memory, pids)?)
+            } else {
+                None
+            };
+            if test_matches.is_present("all") {
+                let jobs = match test_matches.value_of("jobs") {
+                    Some(jobs) => jobs
+                        .parse::<NonZeroUsize>()
+                        .context("the number of jobs must be a positive integer")?
+                        .get(),
+                    None => thread::available_parallelism().map_or(1, usize::from),
+                };
+                let group = test_matches.value_of("group");
+                let reports =
+                    test_all_problems(&repository, group, jobs, output_format, keep_going, sandbox);
+                return report_results(reporter.as_ref(), reports);
+            }
             let problem = repository.problem_from_path(&path)?;
-            let report = ReportType::from_name(test_matches.value_of("report-to").unwrap())?;
             if let Some(solutions_repo) = test_matches.value_of("move-files") {
                 let checkout_branch = test_matches.value_of("checkout-branch").is_some();
                 let solutions_repo: PathBuf = solutions_repo.into();
                 problem.move_solution_files_from(&solutions_repo, checkout_branch)?;
             }
-            // TODO: Make testing errors more clear
-            let testing_result = test_problem(problem);
-            let report_push = report.push_report(testing_result.is_err());
-            testing_result.and(report_push)
+            if test_matches.is_present("watch") {
+                return watch_problem(problem, keep_going, sandbox);
+            }
+            let report = test_problem(problem, output_format, keep_going, sandbox);
+            report_results(reporter.as_ref(), report.map(|report| vec![report]))
         }
         Some(("compose", compose_matches)) => {
             let input: PathBuf = compose_matches.value_of("input").unwrap().into();
             let output: PathBuf = compose_matches.value_of("output").unwrap().into();
-            run_compose(&input, &output)
+            let verification = if compose_matches.is_present("verify-tests") {
+                Verification::BuildAndTests
+            } else if compose_matches.is_present("verify") {
+                Verification::Build
+            } else {
+                Verification::None
+            };
+            run_compose(&input, &output, verification)
+        }
+        Some(("check-config", check_matches)) => {
+            let path: PathBuf = check_matches.value_of("path").unwrap().into();
+            check_config(&path, check_matches.is_present("all"))
+        }
+        Some(("list", list_matches)) => {
+            let path: PathBuf = list_matches.value_of("path").unwrap().into();
+            list_problems(&path)
+        }
+        Some(("status", status_matches)) => {
+            let path: PathBuf = status_matches.value_of("path").unwrap().into();
+            print_status(&path)
+        }
+        Some(("new-problem", new_matches)) => {
+            let path: PathBuf = new_matches.value_of("path").unwrap().into();
+            let template = Template::from_name(new_matches.value_of("template").unwrap())?;
+            new_problem(
+                &path,
+                new_matches.value_of("group").unwrap(),
+                new_matches.value_of("title").unwrap(),
+                template,
+            )
         }
         _ => unreachable!(),
     }
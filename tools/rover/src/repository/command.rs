@@ -1,54 +1,221 @@
+use crate::linting::items::ItemPattern;
 use anyhow::{bail, Result};
+use serde::{
+    de::{self, value::MapAccessDeserializer, MapAccess},
+    Deserialize, Deserializer,
+};
+use std::{collections::BTreeMap, fmt, time::Duration};
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum Command {
     ForbidUnsafe,
     ForbidCollections,
     ForbidStd,
-    CargoFmt,
-    CargoClippy,
-    CargoTest,
-    CargoTestDebug,
-    CargoMiriTest,
-    CargoCompileTestMiniFrunk,
-    CargoCompileTestOrm,
-    CargoCompileTestSnapshot,
-    PythonTest,
+    ForbidItems(Vec<ItemPattern>),
+    Run(RunCommand),
+}
+
+/// A command described in `.config.yml` as a mapping instead of a built-in name.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum InlineCommand {
+    Run(RunCommand),
+    ForbidItems(Vec<String>),
+}
+
+/// An entry of a step in `.config.yml`: a built-in name or an inline command.
+pub struct CommandSpec(Vec<Command>);
+
+impl CommandSpec {
+    pub fn into_commands(self) -> Vec<Command> {
+        self.0
+    }
+}
+
+impl<'de> Deserialize<'de> for CommandSpec {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct SpecVisitor;
+
+        impl<'de> de::Visitor<'de> for SpecVisitor {
+            type Value = CommandSpec;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(
+                    f,
+                    "a command name or a mapping with \"run\" or \"forbid-items\""
+                )
+            }
+
+            fn visit_str<E: de::Error>(self, name: &str) -> std::result::Result<Self::Value, E> {
+                Command::from_name(name).map(CommandSpec).map_err(E::custom)
+            }
+
+            fn visit_map<A: MapAccess<'de>>(
+                self,
+                map: A,
+            ) -> std::result::Result<Self::Value, A::Error> {
+                let inline = InlineCommand::deserialize(MapAccessDeserializer::new(map))?;
+                Command::from_inline(inline)
+                    .map(|command| CommandSpec(vec![command]))
+                    .map_err(de::Error::custom)
+            }
+        }
+
+        deserializer.deserialize_any(SpecVisitor)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExpectedStatus {
+    #[default]
+    Success,
+    Failure,
+    Code(i32),
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct RunCommand {
+    name: Option<String>,
+    program: String,
+    #[serde(default)]
+    args: Vec<String>,
+    #[serde(default)]
+    env: BTreeMap<String, String>,
+    #[serde(default)]
+    expect: ExpectedStatus,
+    #[serde(default, with = "humantime_serde")]
+    timeout: Option<Duration>,
+}
+
+impl RunCommand {
+    fn new(name: &str, shell_line: &str) -> Self {
+        let mut iter = shell_line.split(' ').map(str::to_string);
+        Self {
+            name: Some(name.to_string()),
+            program: iter.next().unwrap(),
+            args: iter.collect(),
+            env: BTreeMap::new(),
+            expect: ExpectedStatus::Success,
+            timeout: None,
+        }
+    }
+
+    fn expect(mut self, expect: ExpectedStatus) -> Self {
+        self.expect = expect;
+        self
+    }
+
+    pub fn name(&self) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => self.get_shell_line(),
+        }
+    }
+
+    pub fn program(&self) -> &str {
+        &self.program
+    }
+
+    pub fn args(&self) -> &[String] {
+        self.args.as_slice()
+    }
+
+    pub fn env(&self) -> &BTreeMap<String, String> {
+        &self.env
+    }
+
+    pub fn expected_status(&self) -> ExpectedStatus {
+        self.expect
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// Whether the command runs the tests of the crate, e.g. `cargo test` or `cargo miri test`.
+    pub fn is_cargo_test(&self) -> bool {
+        self.program == "cargo"
+            && self
+                .args
+                .iter()
+                .take_while(|arg| *arg != "--")
+                .any(|arg| arg == "test")
+    }
+
+    pub fn get_shell_line(&self) -> String {
+        std::iter::once(&self.program)
+            .chain(self.args.iter())
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
 }
 
 impl Command {
-    pub fn from_name(name: &str) -> Result<Self> {
+    /// Resolves a built-in command alias. One alias may expand into several commands.
+    pub fn from_name(name: &str) -> Result<Vec<Self>> {
+        let run = |shell_line: &str| Self::Run(RunCommand::new(name, shell_line));
+        let compile_fail = |feature: &str| {
+            Self::Run(
+                RunCommand::new(
+                    &format!("{name}/{feature}"),
+                    &format!("cargo test --features {feature}"),
+                )
+                .expect(ExpectedStatus::Failure),
+            )
+        };
         Ok(match name {
-            "forbid-unsafe" => Self::ForbidUnsafe,
-            "forbid-collections" => Self::ForbidCollections,
-            "forbid-std" => Self::ForbidStd,
-            "cargo-fmt" => Self::CargoFmt,
-            "cargo-clippy" => Self::CargoClippy,
-            "cargo-test" => Self::CargoTest,
-            "cargo-test-debug" => Self::CargoTestDebug,
-            "cargo-miri-test" => Self::CargoMiriTest,
-            "cargo-compile-test-mini-frunk" => Self::CargoCompileTestMiniFrunk,
-            "cargo-compile-test-orm" => Self::CargoCompileTestOrm,
-            "cargo-compile-test-snapshot" => Self::CargoCompileTestSnapshot,
-            "python-test" => Self::PythonTest,
+            "forbid-unsafe" => vec![Self::ForbidUnsafe],
+            "forbid-collections" => vec![Self::ForbidCollections],
+            "forbid-std" => vec![Self::ForbidStd],
+            "cargo-fmt" => vec![run("cargo fmt --check")],
+            "cargo-clippy" => vec![run("cargo clippy --release -- -D warnings")],
+            "cargo-test" => vec![run("cargo test --release")],
+            "cargo-test-debug" => vec![run("cargo test")],
+            "cargo-miri-test" => vec![run("cargo miri test --release")],
+            "cargo-compile-test-mini-frunk" => vec![
+                compile_fail("compilation-fail-generic"),
+                compile_fail("compilation-fail-labelled"),
+                compile_fail("compilation-fail-transmogrify"),
+            ],
+            "cargo-compile-test-orm" => vec![
+                compile_fail("test-lifetimes-create"),
+                compile_fail("test-lifetimes-get"),
+            ],
+            "cargo-compile-test-snapshot" => vec![compile_fail("test-lifetime")],
+            "python-test" => vec![run("python3 test.py")],
             name => bail!("command \"{name}\" is not supported"),
         })
     }
 
-    pub fn get_shell_line(&self) -> Result<String> {
-        Ok(match self {
-            Self::ForbidUnsafe => bail!("no shell line for ForbidUnsafe"),
-            Self::ForbidCollections => bail!("no shell line for ForbidCollections"),
-            Self::ForbidStd => bail!("no shell line for ForbidStd"),
-            Self::CargoFmt => "cargo fmt --check".to_string(),
-            Self::CargoClippy => "cargo clippy --release -- -D warnings".to_string(),
-            Self::CargoTest => "cargo test --release".to_string(),
-            Self::CargoTestDebug => "cargo test".to_string(),
-            Self::CargoMiriTest => "cargo miri test --release".to_string(),
-            Self::CargoCompileTestMiniFrunk => bail!("no shell line for CargoCompileTestMiniFrunk"),
-            Self::CargoCompileTestOrm => bail!("no shell line for CargoCompileTestOrm"),
-            Self::CargoCompileTestSnapshot => bail!("no shell line for CargoCompileTestSnapshot"),
-            Self::PythonTest => "python3 test.py".to_string(),
+    fn from_inline(inline: InlineCommand) -> Result<Self> {
+        Ok(match inline {
+            InlineCommand::Run(run) => Self::Run(run),
+            InlineCommand::ForbidItems(items) => Self::ForbidItems(
+                items
+                    .iter()
+                    .map(|item| ItemPattern::parse(item))
+                    .collect::<Result<_>>()?,
+            ),
         })
     }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        match self {
+            Self::Run(run) => run.timeout(),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Self::ForbidUnsafe => "forbid-unsafe".to_string(),
+            Self::ForbidCollections => "forbid-collections".to_string(),
+            Self::ForbidStd => "forbid-std".to_string(),
+            Self::ForbidItems(_) => "forbid-items".to_string(),
+            Self::Run(run) => run.name(),
+        }
+    }
 }
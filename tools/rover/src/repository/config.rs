@@ -1,66 +1,95 @@
-#![allow(clippy::needless_question_mark)]
-
-use super::{command::Command, context::CommandContext, step::Step, toolchain::Toolchain};
+use super::{
+    command::CommandSpec,
+    context::{CommandContext, LaunchOptions},
+    step::Step,
+    toolchain::Toolchain,
+};
+use crate::testing::score::Scoring;
 use anyhow::{Context, Result};
-use glob::{glob_with, MatchOptions};
-use serde_yaml::{from_reader, Value};
+use glob::{glob_with, MatchOptions, Pattern};
+use serde::{
+    de::{self, value::MapAccessDeserializer, value::SeqAccessDeserializer, MapAccess, SeqAccess},
+    Deserialize, Deserializer,
+};
 use std::{
+    fmt,
     fs::File,
     path::{Path, PathBuf},
+    time::Duration,
 };
 
+const DEFAULT_COMMAND_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// Layout of `.config.yml`.
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct ConfigFile {
+    #[serde(deserialize_with = "deserialize_toolchain")]
+    toolchain: Toolchain,
+    #[serde(default)]
+    components: Vec<String>,
+    #[serde(default, with = "humantime_serde")]
+    timeout: Option<Duration>,
+    allowed_patterns: Vec<String>,
+    #[serde(default)]
+    private_tests: Option<String>,
+    #[serde(default)]
+    scoring: Option<Scoring>,
+    steps: StepsFile,
+}
+
+/// Steps in the order they're written in the file.
+struct StepsFile(Vec<Step>);
+
+/// A step is either a list of commands or a mapping with commands and timeout.
+enum StepFile {
+    Commands(Vec<CommandSpec>),
+    Mapping(StepMapping),
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct StepMapping {
+    commands: Vec<CommandSpec>,
+    #[serde(default, with = "humantime_serde")]
+    timeout: Option<Duration>,
+}
+
 #[derive(Debug)]
 pub struct Config {
     workdir: PathBuf,
     toolchain: Toolchain,
+    components: Vec<String>,
+    timeout: Duration,
+    patterns: Vec<String>,
+    unmatched_patterns: Vec<String>,
     relative_user_files: Vec<PathBuf>,
     absolute_user_files: Vec<PathBuf>,
+    private_tests: Option<String>,
+    scoring: Option<Scoring>,
     steps: Vec<Step>,
 }
 
 impl Config {
     pub fn from_yml(path: &Path) -> Result<Self> {
-        let file = File::open(path).context("no yml file with config")?;
-        let yml: Value = from_reader(file).context("unable to read yml file with config")?;
-        let yml = yml.as_mapping().context("the yml is not a mapping")?;
+        let file = File::open(path).with_context(|| format!("no yml file with config {path:?}"))?;
+        let config: ConfigFile =
+            serde_yaml::from_reader(file).with_context(|| format!("invalid config {path:?}"))?;
         let workdir = path.parent().context("yml has no parent")?.to_path_buf();
-        let toolchain = Toolchain::from_name(
-            yml[&Value::String("toolchain".to_string())]
-                .as_str()
-                .context("toolchain is not a string")?,
-        )?;
-        let patterns = yml[&Value::String("allowed-patterns".to_string())]
-            .as_sequence()
-            .context("user files are not in a list")?
-            .iter()
-            .map(|value| Ok(value.as_str().context("user file path is not a string")?))
-            .collect::<Result<Vec<_>>>()?;
-        let (relative_user_files, absolute_user_files) =
-            Self::get_matching_user_files(&workdir, patterns.as_slice())?;
-        let steps = yml[&Value::String("steps".to_string())]
-            .as_mapping()
-            .context("steps is not a mapping")?
-            .iter()
-            .map(|(key, value)| {
-                let name = key
-                    .as_str()
-                    .context("name of the step is not a string")?
-                    .to_string();
-                let commands = value
-                    .as_sequence()
-                    .context("the step commands are not a sequence")?
-                    .iter()
-                    .map(|value| Command::from_name(value.as_str().unwrap()).unwrap())
-                    .collect();
-                Ok(Step::new(name, commands))
-            })
-            .collect::<Result<Vec<_>>>()?;
+        let (relative_user_files, absolute_user_files, unmatched_patterns) =
+            Self::get_matching_user_files(&workdir, config.allowed_patterns.as_slice())?;
         Ok(Self {
             workdir,
-            toolchain,
+            toolchain: config.toolchain,
+            components: config.components,
+            timeout: config.timeout.unwrap_or(DEFAULT_COMMAND_TIMEOUT),
+            patterns: config.allowed_patterns,
+            unmatched_patterns,
             relative_user_files,
             absolute_user_files,
-            steps,
+            private_tests: config.private_tests,
+            scoring: config.scoring,
+            steps: config.steps.0,
         })
     }
 
@@ -72,40 +101,271 @@ impl Config {
         &self.toolchain
     }
 
+    /// Rustup components the steps need besides the toolchain, e.g. `miri`.
+    pub fn get_components(&self) -> &[String] {
+        self.components.as_slice()
+    }
+
+    /// Timeout of a command that doesn't set its own one.
+    pub fn get_timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    pub fn get_patterns(&self) -> &[String] {
+        self.patterns.as_slice()
+    }
+
+    /// Allowed patterns that match no files, most likely a typo in the config.
+    pub fn get_unmatched_patterns(&self) -> &[String] {
+        self.unmatched_patterns.as_slice()
+    }
+
     pub fn get_relative_user_files(&self) -> &[PathBuf] {
         self.relative_user_files.as_slice()
     }
 
-    #[allow(dead_code)]
     pub fn get_absolute_user_files(&self) -> &[PathBuf] {
         self.absolute_user_files.as_slice()
     }
 
-    pub fn get_command_context(&self) -> CommandContext {
-        CommandContext::new(&self.workdir, self.absolute_user_files.as_slice())
+    /// Checks whether a path relative to the problem matches the allowed patterns.
+    pub fn is_user_file(&self, path: &Path) -> Result<bool> {
+        for pattern in &self.patterns {
+            let pattern = Pattern::new(pattern)
+                .with_context(|| format!("pattern \"{pattern}\" is invalid"))?;
+            if pattern.matches_path_with(path, Self::MATCH_OPTIONS) {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Directory with the tests added to `tests` while the tests are running, as it's
+    /// written in the config.
+    pub fn get_private_tests(&self) -> Option<&str> {
+        self.private_tests.as_deref()
+    }
+
+    pub fn get_scoring(&self) -> Option<&Scoring> {
+        self.scoring.as_ref()
+    }
+
+    pub fn get_workdir(&self) -> &Path {
+        &self.workdir
+    }
+
+    pub fn get_command_context(&self, options: &LaunchOptions) -> CommandContext {
+        CommandContext::new(&self.workdir, self.absolute_user_files.as_slice(), options)
     }
 
+    const MATCH_OPTIONS: MatchOptions = MatchOptions {
+        case_sensitive: false,
+        require_literal_separator: false,
+        require_literal_leading_dot: false,
+    };
+
     fn get_matching_user_files(
         workdir: &Path,
-        patterns: &[&str],
-    ) -> Result<(Vec<PathBuf>, Vec<PathBuf>)> {
-        let options = MatchOptions {
-            case_sensitive: false,
-            require_literal_separator: false,
-            require_literal_leading_dot: false,
-        };
+        patterns: &[String],
+    ) -> Result<(Vec<PathBuf>, Vec<PathBuf>, Vec<String>)> {
         let workdir_len = workdir.iter().count();
         let mut relative_user_files = Vec::new();
         let mut absolute_user_files = Vec::new();
+        let mut unmatched_patterns = Vec::new();
         for pattern in patterns {
-            let pattern = workdir.join(pattern).to_path_buf();
-            let pattern = pattern.to_str().context("non-utf-8 path")?;
-            for entry in glob_with(pattern, options).context("pattern is invalid")? {
+            let full_pattern = workdir.join(pattern).to_path_buf();
+            let full_pattern = full_pattern.to_str().context("non-utf-8 path")?;
+            let mut entries = glob_with(full_pattern, Self::MATCH_OPTIONS)
+                .with_context(|| format!("pattern \"{pattern}\" is invalid"))?
+                .peekable();
+            if entries.peek().is_none() {
+                unmatched_patterns.push(pattern.clone());
+            }
+            for entry in entries {
                 let entry = entry?;
                 absolute_user_files.push(entry.clone());
                 relative_user_files.push(entry.iter().skip(workdir_len).collect());
             }
         }
-        Ok((relative_user_files, absolute_user_files))
+        Ok((relative_user_files, absolute_user_files, unmatched_patterns))
+    }
+}
+
+fn deserialize_toolchain<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Toolchain, D::Error> {
+    struct ToolchainVisitor;
+
+    impl<'de> de::Visitor<'de> for ToolchainVisitor {
+        type Value = Toolchain;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "a toolchain name")
+        }
+
+        fn visit_str<E: de::Error>(self, name: &str) -> std::result::Result<Self::Value, E> {
+            Toolchain::from_name(name).map_err(E::custom)
+        }
+    }
+
+    deserializer.deserialize_str(ToolchainVisitor)
+}
+
+impl<'de> Deserialize<'de> for StepsFile {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct StepsVisitor;
+
+        impl<'de> de::Visitor<'de> for StepsVisitor {
+            type Value = StepsFile;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a mapping from step names to steps")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(
+                self,
+                mut map: A,
+            ) -> std::result::Result<Self::Value, A::Error> {
+                let mut steps = Vec::new();
+                while let Some(name) = map.next_key::<String>()? {
+                    let (commands, timeout) = match map.next_value()? {
+                        StepFile::Commands(commands) => (commands, None),
+                        StepFile::Mapping(step) => (step.commands, step.timeout),
+                    };
+                    let commands = commands
+                        .into_iter()
+                        .flat_map(CommandSpec::into_commands)
+                        .collect();
+                    steps.push(Step::new(name, commands, timeout));
+                }
+                Ok(StepsFile(steps))
+            }
+        }
+
+        deserializer.deserialize_map(StepsVisitor)
+    }
+}
+
+impl<'de> Deserialize<'de> for StepFile {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct StepVisitor;
+
+        impl<'de> de::Visitor<'de> for StepVisitor {
+            type Value = StepFile;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a list of commands or a mapping with \"commands\"")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(
+                self,
+                seq: A,
+            ) -> std::result::Result<Self::Value, A::Error> {
+                Deserialize::deserialize(SeqAccessDeserializer::new(seq)).map(StepFile::Commands)
+            }
+
+            fn visit_map<A: MapAccess<'de>>(
+                self,
+                map: A,
+            ) -> std::result::Result<Self::Value, A::Error> {
+                Deserialize::deserialize(MapAccessDeserializer::new(map)).map(StepFile::Mapping)
+            }
+        }
+
+        deserializer.deserialize_any(StepVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    const ALIASES: [&str; 12] = [
+        "forbid-unsafe",
+        "forbid-collections",
+        "forbid-std",
+        "cargo-fmt",
+        "cargo-clippy",
+        "cargo-test",
+        "cargo-test-debug",
+        "cargo-miri-test",
+        "cargo-compile-test-mini-frunk",
+        "cargo-compile-test-orm",
+        "cargo-compile-test-snapshot",
+        "python-test",
+    ];
+
+    fn parse(steps: &str) -> std::result::Result<ConfigFile, String> {
+        let yml = format!("toolchain: stable\nallowed-patterns: [src/lib.rs]\nsteps:\n{steps}");
+        serde_yaml::from_str(&yml).map_err(|err| err.to_string())
+    }
+
+    #[test]
+    fn parses_steps() {
+        let config = parse(
+            "  lint: [cargo-fmt, forbid-items: [std::sync::mpsc]]
+  test:
+    commands:
+      - cargo-compile-test-orm
+      - run:
+          program: cargo
+          args: [test]
+    timeout: 1m
+",
+        )
+        .unwrap();
+        let steps = config.steps.0;
+        assert_eq!(steps.len(), 2);
+        assert_eq!(steps[0].name(), "lint");
+        assert_eq!(steps[0].commands().len(), 2);
+        assert_eq!(steps[1].commands().len(), 3);
+        assert_eq!(steps[1].timeout(), Some(Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn parses_aliases() {
+        for alias in ALIASES {
+            parse(&format!("  step: [{alias}]\n")).unwrap();
+        }
+    }
+
+    #[test]
+    fn parses_course_configs() {
+        let problems = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../problems");
+        for group in fs::read_dir(problems).unwrap() {
+            for problem in fs::read_dir(group.unwrap().path()).unwrap() {
+                let path = problem.unwrap().path().join(".config.yml");
+                if let Ok(yml) = fs::read_to_string(&path) {
+                    if let Err(err) = serde_yaml::from_str::<ConfigFile>(&yml) {
+                        panic!("{path:?}: {err}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn reports_errors_with_lines() {
+        for (steps, error) in [
+            (
+                "  lint: [cargo-fmt, cargo-lint]\n",
+                "command \"cargo-lint\" is not supported",
+            ),
+            (
+                "  test:\n    commands: [cargo-test]\n    retries: 2\n",
+                "unknown field `retries`",
+            ),
+            (
+                "  test:\n    commands: [cargo-test]\n    timeout: [1]\n",
+                "invalid type",
+            ),
+            ("  test: cargo-test\n", "invalid type"),
+            ("  test: [run: {program: [cargo]}]\n", "invalid type"),
+        ] {
+            let err = parse(steps).err().expect(steps);
+            assert!(err.contains(error), "{err}");
+            assert!(err.contains("line"), "{err}");
+        }
     }
 }
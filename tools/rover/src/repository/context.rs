@@ -1,18 +1,104 @@
+use super::sandbox::Sandbox;
 use std::path::{Path, PathBuf};
 
+/// Where the output of launched commands is echoed while it's being captured.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Echo {
+    Inherit,
+    Stderr,
+    /// Only capture, used when several problems are tested at once.
+    Quiet,
+}
+
+/// How the steps of a problem are launched.
+pub struct LaunchOptions {
+    echo: Echo,
+    keep_going: bool,
+    target_dir: Option<PathBuf>,
+    sandbox: Option<Sandbox>,
+}
+
+impl LaunchOptions {
+    pub fn new(echo: Echo) -> Self {
+        Self {
+            echo,
+            keep_going: false,
+            target_dir: None,
+            sandbox: None,
+        }
+    }
+
+    /// Launch all the steps even if some of them fail.
+    pub fn keep_going(mut self, keep_going: bool) -> Self {
+        self.keep_going = keep_going;
+        self
+    }
+
+    /// Cargo target directory for the commands instead of the one of the workspace.
+    pub fn target_dir(mut self, target_dir: PathBuf) -> Self {
+        self.target_dir = Some(target_dir);
+        self
+    }
+
+    /// Launch the commands in the sandbox.
+    pub fn sandbox(mut self, sandbox: Option<Sandbox>) -> Self {
+        self.sandbox = sandbox;
+        self
+    }
+
+    pub fn get_echo(&self) -> Echo {
+        self.echo
+    }
+
+    pub fn get_keep_going(&self) -> bool {
+        self.keep_going
+    }
+
+    pub fn get_target_dir(&self) -> Option<&Path> {
+        self.target_dir.as_deref()
+    }
+
+    pub fn get_sandbox(&self) -> Option<&Sandbox> {
+        self.sandbox.as_ref()
+    }
+}
+
 pub struct CommandContext {
     workdir: PathBuf,
     user_files: Vec<PathBuf>,
+    echo: Echo,
+    target_dir: Option<PathBuf>,
+    sandbox: Option<Sandbox>,
+    private_tests: Option<PathBuf>,
+    libtest_json: bool,
 }
 
 impl CommandContext {
-    pub fn new(workdir: &Path, user_files: &[PathBuf]) -> Self {
+    pub fn new(workdir: &Path, user_files: &[PathBuf], options: &LaunchOptions) -> Self {
         Self {
             workdir: workdir.to_path_buf(),
             user_files: user_files.to_vec(),
+            echo: options.get_echo(),
+            target_dir: options.get_target_dir().map(Path::to_path_buf),
+            sandbox: options.get_sandbox().cloned(),
+            private_tests: None,
+            libtest_json: false,
         }
     }
 
+    /// Private tests to add to the tests of the problem while they're running.
+    pub fn private_tests(mut self, private_tests: Option<PathBuf>) -> Self {
+        self.private_tests = private_tests;
+        self
+    }
+
+    /// Ask libtest for the JSON output, so the outcomes of tests are read reliably.
+    /// It's an unstable option available only on nightly.
+    pub fn libtest_json(mut self, libtest_json: bool) -> Self {
+        self.libtest_json = libtest_json;
+        self
+    }
+
     pub fn get_workdir(&self) -> &Path {
         &self.workdir
     }
@@ -20,4 +106,24 @@ impl CommandContext {
     pub fn get_user_files(&self) -> &[PathBuf] {
         &self.user_files
     }
+
+    pub fn get_echo(&self) -> Echo {
+        self.echo
+    }
+
+    pub fn get_target_dir(&self) -> Option<&Path> {
+        self.target_dir.as_deref()
+    }
+
+    pub fn get_sandbox(&self) -> Option<&Sandbox> {
+        self.sandbox.as_ref()
+    }
+
+    pub fn get_private_tests(&self) -> Option<&Path> {
+        self.private_tests.as_deref()
+    }
+
+    pub fn get_libtest_json(&self) -> bool {
+        self.libtest_json
+    }
 }
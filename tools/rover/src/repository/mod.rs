@@ -1,8 +1,11 @@
 mod command;
 mod config;
-mod context;
+pub mod context;
 mod copying;
+pub mod overlay;
 pub mod problem;
 pub mod repo;
+pub mod sandbox;
+pub mod solutions;
 mod step;
 mod toolchain;
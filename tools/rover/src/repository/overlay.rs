@@ -0,0 +1,333 @@
+use anyhow::{bail, Context, Result};
+use nix::{errno::Errno, sys::signal::kill, unistd::Pid};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    process,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+/// Resolves `private-tests` of the config: `$NAME` at the start is replaced with the
+/// environment variable, a relative path is relative to the problem. Returns `None` if
+/// the variable is not set, i.e. the private tests are not available here.
+pub fn resolve_private_tests(workdir: &Path, path: &str) -> Result<Option<PathBuf>> {
+    let path = match path.strip_prefix('$') {
+        Some(path) => {
+            let (name, rest) = path.split_once('/').unwrap_or((path, ""));
+            match env::var_os(name) {
+                Some(value) => PathBuf::from(value).join(rest),
+                None => return Ok(None),
+            }
+        }
+        None => PathBuf::from(path),
+    };
+    let path = workdir.join(path);
+    if !path.is_dir() {
+        bail!("private tests {path:?} are not a directory")
+    }
+    Ok(Some(path))
+}
+
+/// Overlays that are applied now, restored by the interrupt handler.
+static ACTIVE_OVERLAYS: Mutex<BTreeMap<u64, Journal>> = Mutex::new(BTreeMap::new());
+static NEXT_OVERLAY_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Everything an overlay has changed. It's saved to the journal directory before each
+/// change, so the changes of a crashed run can be undone by the next one.
+#[derive(Default, Serialize, Deserialize)]
+struct Journal {
+    created_files: Vec<PathBuf>,
+    created_dirs: Vec<PathBuf>,
+    replaced_files: Vec<(PathBuf, Vec<u8>)>,
+}
+
+impl Journal {
+    fn save(&self, path: &Path) -> Result<()> {
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, serde_json::to_vec(self)?)
+            .with_context(|| format!("failed to write {tmp_path:?}"))?;
+        fs::rename(&tmp_path, path).with_context(|| format!("failed to write {path:?}"))
+    }
+
+    /// Files that were not created yet are skipped: the journal is saved before the change.
+    fn restore(&self) -> Result<()> {
+        for file in &self.created_files {
+            match fs::remove_file(file) {
+                Err(err) if err.kind() != ErrorKind::NotFound => {
+                    return Err(err).with_context(|| format!("failed to remove {file:?}"))
+                }
+                _ => {}
+            }
+        }
+        for (file, content) in &self.replaced_files {
+            fs::write(file, content).with_context(|| format!("failed to restore {file:?}"))?;
+        }
+        // The nested directories are created after their parents.
+        for dir in self.created_dirs.iter().rev() {
+            match fs::remove_dir(dir) {
+                Err(err) if err.kind() != ErrorKind::NotFound => {
+                    return Err(err).with_context(|| format!("failed to remove {dir:?}"))
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+fn journal_dir() -> PathBuf {
+    env::temp_dir().join("rover-overlays")
+}
+
+fn journal_path(dir: &Path, pid: u32, id: u64) -> PathBuf {
+    dir.join(format!("{pid}-{id}.json"))
+}
+
+/// Private tests copied into the tests directory of the problem. Everything is restored
+/// as it was when the overlay is dropped or rover is interrupted.
+pub struct TestOverlay {
+    id: u64,
+    journal_path: PathBuf,
+}
+
+impl TestOverlay {
+    pub fn apply(private_tests: &Path, tests_dir: &Path) -> Result<Self> {
+        Self::apply_with_journal(private_tests, tests_dir, &journal_dir())
+    }
+
+    fn apply_with_journal(
+        private_tests: &Path,
+        tests_dir: &Path,
+        journal_dir: &Path,
+    ) -> Result<Self> {
+        fs::create_dir_all(journal_dir)
+            .with_context(|| format!("failed to create {journal_dir:?}"))?;
+        let id = NEXT_OVERLAY_ID.fetch_add(1, Ordering::Relaxed);
+        let overlay = Self {
+            id,
+            journal_path: journal_path(journal_dir, process::id(), id),
+        };
+        ACTIVE_OVERLAYS
+            .lock()
+            .unwrap()
+            .insert(id, Journal::default());
+        // On error the files copied so far are removed by drop.
+        overlay.copy_dir(private_tests, tests_dir)?;
+        Ok(overlay)
+    }
+
+    /// Records the change in the journal and then makes it, holding the lock so that
+    /// the interrupt handler never sees a change that is not recorded.
+    fn change(
+        &self,
+        record: impl FnOnce(&mut Journal),
+        change: impl FnOnce() -> Result<()>,
+    ) -> Result<()> {
+        let mut overlays = ACTIVE_OVERLAYS.lock().unwrap();
+        let journal = overlays.get_mut(&self.id).unwrap();
+        record(journal);
+        journal.save(&self.journal_path)?;
+        change()
+    }
+
+    fn copy_dir(&self, src: &Path, dest: &Path) -> Result<()> {
+        if !dest.exists() {
+            self.change(
+                |journal| journal.created_dirs.push(dest.to_path_buf()),
+                || fs::create_dir(dest).with_context(|| format!("failed to create {dest:?}")),
+            )?;
+        }
+        let mut entries = fs::read_dir(src)
+            .with_context(|| format!("failed to read {src:?}"))?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        entries.sort();
+        for entry in entries {
+            let dest = dest.join(entry.file_name().unwrap());
+            if entry.is_dir() {
+                self.copy_dir(&entry, &dest)?;
+                continue;
+            }
+            let copy = || {
+                fs::copy(&entry, &dest)
+                    .map(|_| ())
+                    .with_context(|| format!("failed to copy {entry:?} to {dest:?}"))
+            };
+            if dest.is_file() {
+                let content =
+                    fs::read(&dest).with_context(|| format!("failed to read {dest:?}"))?;
+                self.change(
+                    |journal| journal.replaced_files.push((dest.clone(), content)),
+                    copy,
+                )?;
+            } else {
+                self.change(|journal| journal.created_files.push(dest.clone()), copy)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for TestOverlay {
+    fn drop(&mut self) {
+        let mut overlays = ACTIVE_OVERLAYS.lock().unwrap();
+        let Some(journal) = overlays.remove(&self.id) else {
+            return;
+        };
+        match journal.restore() {
+            Ok(()) => {
+                let _ = fs::remove_file(&self.journal_path);
+            }
+            Err(err) => eprintln!("failed to remove private tests: {err:#}"),
+        }
+    }
+}
+
+/// Removes the private tests of the overlays that are applied now. Called when rover
+/// is interrupted, the drops won't run then. The lock is kept, so no overlay changes
+/// anything afterwards.
+pub fn restore_active_overlays() {
+    let mut overlays = ACTIVE_OVERLAYS.lock().unwrap();
+    let dir = journal_dir();
+    for (id, journal) in std::mem::take(&mut *overlays) {
+        match journal.restore() {
+            Ok(()) => {
+                let _ = fs::remove_file(journal_path(&dir, process::id(), id));
+            }
+            Err(err) => eprintln!("failed to remove private tests: {err:#}"),
+        }
+    }
+    std::mem::forget(overlays);
+}
+
+/// Removes the private tests left behind by the runs of rover that crashed or were
+/// killed. The journals of the running processes are left alone.
+pub fn restore_leftover_overlays() -> Result<()> {
+    restore_leftovers(&journal_dir(), |pid| {
+        kill(Pid::from_raw(pid as i32), None) != Err(Errno::ESRCH)
+    })
+}
+
+fn restore_leftovers(dir: &Path, is_running: impl Fn(u32) -> bool) -> Result<()> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err).with_context(|| format!("failed to read {dir:?}")),
+    };
+    for entry in entries {
+        let path = entry?.path();
+        let Some(pid) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.split_once('-'))
+            .and_then(|(pid, _)| pid.parse().ok())
+        else {
+            continue;
+        };
+        if is_running(pid) {
+            continue;
+        }
+        if path.extension().is_some_and(|ext| ext == "tmp") {
+            let _ = fs::remove_file(&path);
+            continue;
+        }
+        let journal: Journal = serde_json::from_slice(
+            &fs::read(&path).with_context(|| format!("failed to read {path:?}"))?,
+        )
+        .with_context(|| format!("failed to parse {path:?}"))?;
+        journal
+            .restore()
+            .context("failed to remove private tests left by an interrupted run")?;
+        fs::remove_file(&path).with_context(|| format!("failed to remove {path:?}"))?;
+        eprintln!("removed private tests left by an interrupted run");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = env::temp_dir().join(format!("rover-test-{}-{name}", process::id()));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn setup(dir: &Path) -> (PathBuf, PathBuf, PathBuf) {
+        let private_tests = dir.join("private");
+        fs::create_dir_all(private_tests.join("data")).unwrap();
+        fs::write(private_tests.join("private.rs"), "private").unwrap();
+        fs::write(private_tests.join("tests.rs"), "private tests").unwrap();
+        fs::write(private_tests.join("data/input.txt"), "input").unwrap();
+
+        let tests_dir = dir.join("tests");
+        fs::create_dir_all(&tests_dir).unwrap();
+        fs::write(tests_dir.join("tests.rs"), "public tests").unwrap();
+        (private_tests, tests_dir, dir.join("journals"))
+    }
+
+    fn assert_restored(tests_dir: &Path) {
+        let entries = fs::read_dir(tests_dir).unwrap().count();
+        assert_eq!(entries, 1);
+        assert_eq!(
+            fs::read_to_string(tests_dir.join("tests.rs")).unwrap(),
+            "public tests"
+        );
+    }
+
+    #[test]
+    fn drop_restores() {
+        let dir = TempDir::new("overlay-drop");
+        let (private_tests, tests_dir, journals) = setup(&dir.0);
+
+        let overlay =
+            TestOverlay::apply_with_journal(&private_tests, &tests_dir, &journals).unwrap();
+        assert_eq!(
+            fs::read_to_string(tests_dir.join("tests.rs")).unwrap(),
+            "private tests"
+        );
+        assert!(tests_dir.join("data/input.txt").is_file());
+        assert_eq!(fs::read_dir(&journals).unwrap().count(), 1);
+
+        drop(overlay);
+        assert_restored(&tests_dir);
+        assert_eq!(fs::read_dir(&journals).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn leftovers_restore() {
+        let dir = TempDir::new("overlay-leftovers");
+        let (private_tests, tests_dir, journals) = setup(&dir.0);
+
+        // A crash: the overlay is never dropped.
+        let overlay =
+            TestOverlay::apply_with_journal(&private_tests, &tests_dir, &journals).unwrap();
+        ACTIVE_OVERLAYS.lock().unwrap().remove(&overlay.id);
+        std::mem::forget(overlay);
+
+        restore_leftovers(&journals, |_| true).unwrap();
+        assert!(tests_dir.join("private.rs").is_file());
+
+        restore_leftovers(&journals, |_| false).unwrap();
+        assert_restored(&tests_dir);
+        assert_eq!(fs::read_dir(&journals).unwrap().count(), 0);
+    }
+}
@@ -1,13 +1,25 @@
-use super::config::Config;
-use super::repo::PROBLEMS_FOLDER;
-use crate::{launch_git, repository::copying::copy_files};
-use anyhow::{bail, Result};
+use super::{
+    config::Config,
+    context::{Echo, LaunchOptions},
+    overlay::resolve_private_tests,
+    repo::PROBLEMS_FOLDER,
+    solutions::SolutionsRepo,
+};
+use crate::{
+    launch_git,
+    repository::copying::copy_files,
+    testing::result::{CommandResult, StepResult, TestReport},
+};
+use anyhow::{bail, Context, Result};
 use std::{
+    ffi::OsStr,
+    fs,
     path::{Path, PathBuf},
     process,
+    time::Instant,
 };
 
-const DEFAULT_YML_NAME: &str = ".config.yml";
+pub const DEFAULT_YML_NAME: &str = ".config.yml";
 
 pub struct Problem {
     path: PathBuf,
@@ -39,6 +51,11 @@ impl Problem {
         self.group() + "/" + &self.title()
     }
 
+    /// Root of the course repository the problem belongs to.
+    pub fn repository_path(&self) -> PathBuf {
+        self.path.ancestors().nth(3).unwrap().to_path_buf()
+    }
+
     pub fn relative_path(&self) -> PathBuf {
         PathBuf::from(PROBLEMS_FOLDER)
             .join(self.group())
@@ -49,16 +66,133 @@ impl Problem {
         Config::from_yml(&self.path.join(DEFAULT_YML_NAME))
     }
 
-    pub fn launch_all_steps(&self) -> Result<()> {
+    /// Launches the steps one by one. Unless `keep_going` is set, the commands after
+    /// the first failure are skipped.
+    pub fn launch_all_steps(&self, options: &LaunchOptions) -> Result<TestReport> {
         let config = self.config()?;
         let toolchain = config.get_toolchain();
-        let context = config.get_command_context();
+        toolchain.preflight(config.get_components())?;
+        let private_tests = match config.get_private_tests() {
+            Some(path) => {
+                let resolved = resolve_private_tests(config.get_workdir(), path)?;
+                if resolved.is_none() {
+                    eprintln!("private tests \"{path}\" are not available, skipping them");
+                }
+                resolved
+            }
+            None => None,
+        };
+        let context = config
+            .get_command_context(options)
+            .private_tests(private_tests)
+            .libtest_json(config.get_scoring().is_some() && toolchain.is_nightly());
+        let keep_going = options.get_keep_going();
+        let mut report = TestReport::new(self.branch_name());
+        let mut failed = false;
         for step in config.get_steps() {
+            let mut step_result = StepResult::new(step.name().to_string());
+            let step_deadline = step.timeout().map(|timeout| Instant::now() + timeout);
             for command in step.commands() {
-                toolchain.run_command(command, &context)?;
+                if failed && !keep_going {
+                    step_result.push(CommandResult::skipped(command.name()));
+                    continue;
+                }
+                let mut timeout = command.timeout().unwrap_or(config.get_timeout());
+                if let Some(deadline) = step_deadline {
+                    timeout = timeout.min(deadline.saturating_duration_since(Instant::now()));
+                }
+                let result = if timeout.is_zero() {
+                    let mut result = CommandResult::new(command.name());
+                    result.time_out(format!("step \"{}\" is out of time", step.name()));
+                    result
+                } else {
+                    toolchain.run_command(command, &context, timeout)
+                };
+                // The progress of several problems tested at once is interleaved.
+                let prefix = match options.get_echo() {
+                    Echo::Quiet => format!("{}: ", self.branch_name()),
+                    Echo::Inherit | Echo::Stderr => String::new(),
+                };
+                eprintln!(
+                    "{prefix}{}/{}: {} in {:.2?}",
+                    step.name(),
+                    result.name(),
+                    result.outcome(),
+                    result.duration()
+                );
+                failed |= result.outcome().is_failure();
+                step_result.push(result);
             }
+            report.push(step_result);
+        }
+        if let Some(scoring) = config.get_scoring() {
+            report.set_score(scoring.score(&report));
+        }
+        Ok(report)
+    }
+
+    /// Checks whether the user files differ from the template of the problem, i.e. from
+    /// their version in the commit that added them to the course repository. A file that
+    /// was never committed counts as changed.
+    pub fn has_changes(&self) -> Result<bool> {
+        let config = self.config()?;
+        for file in config.get_relative_user_files() {
+            let log = self.git([
+                "log".as_ref(),
+                "--diff-filter=A".as_ref(),
+                "--format=%H".as_ref(),
+                "--".as_ref(),
+                file.as_os_str(),
+            ])?;
+            let log = String::from_utf8_lossy(&log);
+            // The commits are listed from the latest one.
+            let Some(added) = log.lines().last() else {
+                return Ok(true);
+            };
+            let file = file.to_str().context("non-utf-8 path")?;
+            let template = self.git(["show", &format!("{added}:./{file}")])?;
+            let path = self.path.join(file);
+            let content = fs::read(&path).with_context(|| format!("failed to read {path:?}"))?;
+            if content != template {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Checks whether the user files have changes not committed to the course repository.
+    pub fn has_uncommitted_changes(&self) -> Result<bool> {
+        let config = self.config()?;
+        if config.get_relative_user_files().is_empty() {
+            return Ok(false);
+        }
+        let mut args = vec!["status".as_ref(), "--porcelain".as_ref(), "--".as_ref()];
+        args.extend(
+            config
+                .get_relative_user_files()
+                .iter()
+                .map(|file| file.as_os_str()),
+        );
+        Ok(!self.git(args)?.is_empty())
+    }
+
+    /// Runs git in the problem directory and returns its stdout.
+    fn git<S: AsRef<OsStr>>(&self, args: impl IntoIterator<Item = S>) -> Result<Vec<u8>> {
+        let args: Vec<_> = args.into_iter().collect();
+        let output = process::Command::new("git")
+            .current_dir(&self.path)
+            .args(&args)
+            .output()
+            .context("failed to launch git")?;
+        if !output.status.success() {
+            bail!(
+                "git {} failed: {}",
+                args.first()
+                    .map_or("".into(), |arg| arg.as_ref().to_string_lossy()),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )
         }
-        Ok(())
+        Ok(output.stdout)
     }
 
     pub fn move_solution_files_from(
@@ -82,35 +216,53 @@ impl Problem {
         )
     }
 
-    pub fn move_solution_files_to(&self, solutions_repo: &Path) -> Result<()> {
+    /// Checks out the branch of the problem in the solutions repository and copies the user
+    /// files there, deleting the user files that were removed from the problem. Returns the
+    /// paths of the files to commit, relative to the solutions repository.
+    pub fn move_solution_files_to(&self, solutions_repo: &SolutionsRepo) -> Result<Vec<PathBuf>> {
         let config = self.config()?;
-        let relative_path = self.relative_path();
-        let branch_name = self.branch_name();
-        let solutions_problem_path = solutions_repo.join(relative_path);
-        let repository_problem_path = self.path.clone();
-        if !launch_git!(solutions_repo, "checkout", &branch_name)
-            && !launch_git!(
-                solutions_repo,
-                "checkout",
-                "-b",
-                &branch_name,
-                "origin/master"
-            )
-            && !launch_git!(
-                solutions_repo,
-                "checkout",
-                "-b",
-                &branch_name,
-                "origin/main"
-            )
-        {
-            bail!("failed to both checkout and create branch in solutions repository")
+        let deleted = self.deleted_solution_files(&config, solutions_repo)?;
+        solutions_repo.checkout_branch(&self.branch_name())?;
+        for file in &deleted {
+            let path = solutions_repo.workdir().join(file);
+            if path.exists() {
+                fs::remove_file(&path).with_context(|| format!("failed to remove {path:?}"))?;
+            }
         }
+        let solution_dir = solutions_repo.workdir().join(self.relative_path());
+        let mut files = self.solution_files(&config);
+        files.extend(deleted);
         copy_files(
-            &repository_problem_path,
-            &solutions_problem_path,
+            &self.path,
+            &solution_dir,
             config.get_relative_user_files(),
             true,
-        )
+        )?;
+        Ok(files)
+    }
+
+    /// User files committed to the branch of the problem in the solutions repository that
+    /// no longer exist in the problem, relative to the solutions repository.
+    pub fn deleted_solution_files(
+        &self,
+        config: &Config,
+        solutions_repo: &SolutionsRepo,
+    ) -> Result<Vec<PathBuf>> {
+        let mut deleted = Vec::new();
+        for file in solutions_repo.files_on_branch(&self.branch_name(), &self.relative_path())? {
+            if config.is_user_file(&file)? && !config.get_relative_user_files().contains(&file) {
+                deleted.push(self.relative_path().join(file));
+            }
+        }
+        Ok(deleted)
+    }
+
+    /// Paths of the user files relative to the solutions repository.
+    pub fn solution_files(&self, config: &Config) -> Vec<PathBuf> {
+        config
+            .get_relative_user_files()
+            .iter()
+            .map(|file| self.relative_path().join(file))
+            .collect()
     }
 }
@@ -1,31 +1,92 @@
-use super::problem::Problem;
+use super::problem::{Problem, DEFAULT_YML_NAME};
 use crate::compose;
 use anyhow::{bail, Context, Result};
-use std::path::{Path, PathBuf};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
 
 pub const COMPOSE_CONFIG: &str = ".compose.yml";
 pub const PROBLEMS_FOLDER: &str = "problems";
 pub const SOLUTIONS_REPO_FOLDER: &str = "solutions";
 pub const REPOSITORY_NAMES: [&str; 2] = ["mipt-rust", "mipt-rust-private"];
+/// Empty file marking the root of a repository without `.compose.yml`.
+pub const REPOSITORY_MARKER: &str = ".rover";
+/// Path to the root of the repository that overrides the detection.
+pub const REPOSITORY_ENV: &str = "ROVER_REPOSITORY";
+/// Comma-separated names of the repository root besides `REPOSITORY_NAMES`.
+pub const REPOSITORY_NAMES_ENV: &str = "ROVER_REPOSITORY_NAMES";
+
+/// Root given with `--repository`, takes precedence over `ROVER_REPOSITORY`.
+static ROOT_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
 
 pub struct Repository {
     path: PathBuf,
 }
 
 impl Repository {
+    /// Sets the root of the repository for every `from_path` call.
+    pub fn override_root(path: PathBuf) {
+        ROOT_OVERRIDE.get_or_init(|| path);
+    }
+
+    /// Finds the root of the repository containing `path`: the nearest directory with
+    /// `.compose.yml` or `.rover`, otherwise the outermost one with a known name.
+    /// An overridden root must contain `path` unless it's the current directory, i.e.
+    /// no path was given.
     pub fn from_path(path: &Path) -> Result<Self> {
         let path = path
             .canonicalize()
             .context("cannot canonicalize path for repository")?;
+        if let Some(root) = ROOT_OVERRIDE
+            .get()
+            .cloned()
+            .or_else(|| env::var_os(REPOSITORY_ENV).map(PathBuf::from))
+        {
+            let root_path = root
+                .canonicalize()
+                .with_context(|| format!("repository {root:?} does not exist"))?;
+            if !root_path.join(PROBLEMS_FOLDER).is_dir() {
+                bail!("repository {root:?} has no \"{PROBLEMS_FOLDER}\" folder")
+            }
+            let is_current_dir = env::current_dir()
+                .and_then(|dir| dir.canonicalize())
+                .is_ok_and(|dir| dir == path);
+            if !path.starts_with(&root_path) && !is_current_dir {
+                bail!(
+                    "{path:?} is outside of repository {root:?} set with --repository or {REPOSITORY_ENV}"
+                )
+            }
+            return Ok(Self { path: root_path });
+        }
+
+        if let Some(root) = path
+            .ancestors()
+            .find(|dir| dir.join(COMPOSE_CONFIG).is_file() || dir.join(REPOSITORY_MARKER).is_file())
+        {
+            return Ok(Self {
+                path: root.to_path_buf(),
+            });
+        }
+        let extra_names = env::var(REPOSITORY_NAMES_ENV).unwrap_or_default();
+        let names: Vec<_> = REPOSITORY_NAMES
+            .into_iter()
+            .chain(extra_names.split(',').map(str::trim))
+            .filter(|name| !name.is_empty())
+            .collect();
         let prefix_count = path
             .iter()
-            .position(|comp| REPOSITORY_NAMES.iter().any(|name| *name == comp));
+            .position(|comp| names.iter().any(|name| *name == comp));
         if let Some(pos) = prefix_count {
             Ok(Self {
                 path: path.iter().take(pos + 1).collect(),
             })
         } else {
-            bail!("path does not contain course repository")
+            bail!(
+                "path does not contain course repository: no \"{COMPOSE_CONFIG}\" or \"{REPOSITORY_MARKER}\" at its root and it's not named {}, set the root with --repository or {REPOSITORY_ENV}",
+                names.join(" or ")
+            )
         }
     }
 
@@ -54,6 +115,26 @@ impl Repository {
         }
     }
 
+    /// All the problems of the repository, i.e. `problems/GROUP/TITLE` with a config.
+    pub fn problems(&self) -> Result<Vec<Problem>> {
+        let mut problems = Vec::new();
+        let groups = self.path.join(PROBLEMS_FOLDER);
+        for group in fs::read_dir(&groups).with_context(|| format!("failed to read {groups:?}"))? {
+            let group = group?.path();
+            if !group.is_dir() {
+                continue;
+            }
+            for problem in fs::read_dir(&group)? {
+                let problem = problem?.path();
+                if problem.join(DEFAULT_YML_NAME).is_file() {
+                    problems.push(Problem::from_path(&problem));
+                }
+            }
+        }
+        problems.sort_by_key(Problem::relative_path);
+        Ok(problems)
+    }
+
     pub fn solutions_repo(&self) -> Result<PathBuf> {
         let path = self.path.parent().unwrap().join(SOLUTIONS_REPO_FOLDER);
         if path.is_dir() {
@@ -67,7 +148,6 @@ impl Repository {
         compose::config::Config::from_yml(&self.path.join(COMPOSE_CONFIG))
     }
 
-    #[allow(unused)]
     pub fn get_path(&self) -> &Path {
         &self.path
     }
@@ -0,0 +1,77 @@
+use anyhow::{bail, Context, Result};
+use std::{
+    ffi::OsStr,
+    fs, io,
+    path::{Path, PathBuf},
+    process,
+};
+
+const BWRAP: &str = "bwrap";
+const PRLIMIT: &str = "prlimit";
+
+/// Jail for the commands launched by the steps: a bubblewrap container with a read-only
+/// view of the host, no network and resource limits set by prlimit. The process limit is
+/// `RLIMIT_NPROC`, which counts every process of the user, not only the sandboxed ones.
+#[derive(Clone, Debug)]
+pub struct Sandbox {
+    repository: PathBuf,
+    memory_limit: u64,
+    pids_limit: u64,
+}
+
+impl Sandbox {
+    pub const DEFAULT_MEMORY_LIMIT_MB: u64 = 4096;
+    pub const DEFAULT_PIDS_LIMIT: u64 = 512;
+
+    /// Fails if the host can't run the sandbox, e.g. bubblewrap is not installed or
+    /// unprivileged user namespaces are disabled.
+    pub fn new(repository: &Path, memory_limit_mb: u64, pids_limit: u64) -> Result<Self> {
+        let sandbox = Self {
+            repository: repository.to_path_buf(),
+            memory_limit: memory_limit_mb.saturating_mul(1024 * 1024),
+            pids_limit,
+        };
+        let output = sandbox
+            .command(repository, None, ["true"])?
+            .stdin(process::Stdio::null())
+            .output();
+        match output {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                bail!("sandbox requires \"{PRLIMIT}\" from util-linux and \"{BWRAP}\" from bubblewrap, install them first")
+            }
+            Err(err) => Err(err).context("failed to launch sandbox"),
+            Ok(output) if !output.status.success() => bail!(
+                "sandbox is not supported on this host: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+            Ok(_) => Ok(sandbox),
+        }
+    }
+
+    /// Command launching the program in the sandbox. The repository and the rest of the
+    /// host are read-only, only the target directory and a private `/tmp` are writable.
+    pub fn command<S: AsRef<OsStr>>(
+        &self,
+        workdir: &Path,
+        target_dir: Option<&Path>,
+        program: impl IntoIterator<Item = S>,
+    ) -> Result<process::Command> {
+        let mut cmd = process::Command::new(PRLIMIT);
+        cmd.arg(format!("--as={}", self.memory_limit))
+            .arg(format!("--nproc={}", self.pids_limit))
+            .args(["--", BWRAP])
+            .args(["--unshare-all", "--die-with-parent", "--new-session"])
+            .args(["--ro-bind", "/", "/", "--dev", "/dev", "--proc", "/proc"])
+            .args(["--tmpfs", "/tmp"])
+            // The repository may be in /tmp itself.
+            .arg("--ro-bind")
+            .args([&self.repository, &self.repository]);
+        if let Some(target_dir) = target_dir {
+            fs::create_dir_all(target_dir)
+                .with_context(|| format!("failed to create {target_dir:?}"))?;
+            cmd.arg("--bind").args([target_dir, target_dir]);
+        }
+        cmd.arg("--chdir").arg(workdir).arg("--").args(program);
+        Ok(cmd)
+    }
+}
@@ -0,0 +1,259 @@
+use anyhow::{anyhow, bail, Context, Result};
+use git2::{
+    build::CheckoutBuilder, BranchType, Commit, ObjectType, Repository, StatusOptions,
+    TreeWalkMode, TreeWalkResult,
+};
+use std::path::{Path, PathBuf};
+
+const REMOTE: &str = "origin";
+const BASE_BRANCHES: [&str; 2] = ["main", "master"];
+
+/// The git repository with the solutions of a student, a branch per problem.
+pub struct SolutionsRepo {
+    repo: Repository,
+}
+
+impl SolutionsRepo {
+    pub fn open(path: &Path) -> Result<Self> {
+        let repo = Repository::open(path)
+            .with_context(|| format!("{path:?} is not the root of a git repository"))?;
+        if repo.is_bare() {
+            bail!("solutions repository {path:?} is bare")
+        }
+        Ok(Self { repo })
+    }
+
+    pub fn workdir(&self) -> &Path {
+        self.repo.workdir().unwrap()
+    }
+
+    /// Checks that the repository may be switched between branches and pushed.
+    pub fn ensure_ready(&self) -> Result<()> {
+        if self.repo.find_remote(REMOTE).is_err() {
+            bail!(
+                "solutions repository has no remote \"{REMOTE}\", add it with \"git remote add {REMOTE} URL\""
+            )
+        }
+        if self.repo.head_detached().unwrap_or(false) {
+            let head = self.repo.head()?.peel_to_commit()?;
+            bail!(
+                "HEAD of solutions repository is detached at {}, checkout a branch first",
+                short_id(&head)
+            )
+        }
+        let mut options = StatusOptions::new();
+        options.include_untracked(false).include_ignored(false);
+        let statuses = self
+            .repo
+            .statuses(Some(&mut options))
+            .context("failed to get status of solutions repository")?;
+        let dirty: Vec<_> = statuses
+            .iter()
+            .filter_map(|entry| entry.path().map(str::to_string))
+            .collect();
+        if !dirty.is_empty() {
+            bail!(
+                "solutions repository has uncommitted changes in {}, commit or stash them first",
+                dirty.join(", ")
+            )
+        }
+        Ok(())
+    }
+
+    /// Content of the file as it is on the branch now. A branch that does not exist yet
+    /// will be created from the remote one or from the base branch.
+    pub fn file_on_branch(&self, branch: &str, path: &Path) -> Result<Option<Vec<u8>>> {
+        let commit = match self.repo.find_branch(branch, BranchType::Local) {
+            Ok(branch) => branch.get().peel_to_commit()?,
+            Err(_) => self.start_commit(branch)?,
+        };
+        let entry = match commit.tree()?.get_path(path) {
+            Ok(entry) => entry,
+            Err(_) => return Ok(None),
+        };
+        let blob = entry
+            .to_object(&self.repo)?
+            .into_blob()
+            .map_err(|_| anyhow!("{path:?} is not a file on branch \"{branch}\""))?;
+        Ok(Some(blob.content().to_vec()))
+    }
+
+    /// Paths of the files in `dir` on the branch, relative to `dir`.
+    pub fn files_on_branch(&self, branch: &str, dir: &Path) -> Result<Vec<PathBuf>> {
+        let commit = match self.repo.find_branch(branch, BranchType::Local) {
+            Ok(branch) => branch.get().peel_to_commit()?,
+            Err(_) => self.start_commit(branch)?,
+        };
+        let tree = match commit.tree()?.get_path(dir) {
+            Ok(entry) => match entry.to_object(&self.repo)?.into_tree() {
+                Ok(tree) => tree,
+                Err(_) => return Ok(Vec::new()),
+            },
+            Err(_) => return Ok(Vec::new()),
+        };
+        let mut files = Vec::new();
+        tree.walk(TreeWalkMode::PreOrder, |root, entry| {
+            if entry.kind() == Some(ObjectType::Blob) {
+                if let Some(name) = entry.name() {
+                    files.push(Path::new(root).join(name));
+                }
+            }
+            TreeWalkResult::Ok
+        })?;
+        Ok(files)
+    }
+
+    /// Checks out the branch, creating it from the remote one or from the base branch.
+    pub fn checkout_branch(&self, name: &str) -> Result<()> {
+        let branch = match self.repo.find_branch(name, BranchType::Local) {
+            Ok(branch) => branch,
+            Err(_) => {
+                let start = self.start_commit(name)?;
+                let mut branch = self
+                    .repo
+                    .branch(name, &start, false)
+                    .with_context(|| format!("failed to create branch \"{name}\""))?;
+                let upstream = format!("{REMOTE}/{name}");
+                if self.repo.find_branch(&upstream, BranchType::Remote).is_ok() {
+                    branch.set_upstream(Some(&upstream))?;
+                }
+                branch
+            }
+        };
+        let commit = branch.get().peel_to_commit()?;
+        self.repo
+            .checkout_tree(commit.as_object(), Some(CheckoutBuilder::new().safe()))
+            .with_context(|| {
+                format!("failed to checkout branch \"{name}\", some untracked files are in the way")
+            })?;
+        self.repo
+            .set_head(branch.get().name().context("non-utf-8 branch name")?)
+            .with_context(|| format!("failed to switch to branch \"{name}\""))
+    }
+
+    /// Commits the files, the paths are relative to the root of the repository. The files
+    /// missing from the working tree are committed as deleted.
+    pub fn commit_files(&self, paths: &[impl AsRef<Path>], message: &str) -> Result<()> {
+        let mut index = self.repo.index()?;
+        for path in paths {
+            let path = path.as_ref();
+            if self.workdir().join(path).exists() {
+                index
+                    .add_path(path)
+                    .with_context(|| format!("failed to add {path:?} to index"))?;
+            } else {
+                index
+                    .remove_path(path)
+                    .with_context(|| format!("failed to remove {path:?} from index"))?;
+            }
+        }
+        index.write()?;
+        let tree = self.repo.find_tree(index.write_tree()?)?;
+        let parent = self.repo.head()?.peel_to_commit()?;
+        if parent.tree_id() == tree.id() {
+            bail!("no changes since the last commit")
+        }
+        let signature = self
+            .repo
+            .signature()
+            .context("git user is not set, configure \"user.name\" and \"user.email\"")?;
+        self.repo
+            .commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                message,
+                &tree,
+                &[&parent],
+            )
+            .context("failed to commit")?;
+        Ok(())
+    }
+
+    fn start_commit(&self, branch: &str) -> Result<Commit<'_>> {
+        if let Ok(remote) = self
+            .repo
+            .find_branch(&format!("{REMOTE}/{branch}"), BranchType::Remote)
+        {
+            return Ok(remote.get().peel_to_commit()?);
+        }
+        for base in BASE_BRANCHES {
+            if let Ok(base) = self
+                .repo
+                .find_branch(&format!("{REMOTE}/{base}"), BranchType::Remote)
+            {
+                return Ok(base.get().peel_to_commit()?);
+            }
+        }
+        bail!(
+            "neither {REMOTE}/main nor {REMOTE}/master exists to create branch \"{branch}\" from, fetch the remote with \"git fetch {REMOTE}\""
+        )
+    }
+}
+
+fn short_id(commit: &Commit) -> String {
+    commit.id().to_string()[..7].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs, process};
+
+    #[test]
+    fn commit_deleted_files() {
+        let dir = env::temp_dir().join(format!("rover-test-{}-solutions", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let repo = Repository::init(&dir).unwrap();
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Student").unwrap();
+        config.set_str("user.email", "student@example.com").unwrap();
+
+        // The initial commit.
+        {
+            let signature = repo.signature().unwrap();
+            let tree = repo
+                .find_tree(repo.index().unwrap().write_tree().unwrap())
+                .unwrap();
+            repo.commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+                .unwrap();
+        }
+
+        let solutions = SolutionsRepo { repo };
+        fs::create_dir_all(dir.join("g/t/src")).unwrap();
+        fs::write(dir.join("g/t/src/lib.rs"), "lib").unwrap();
+        fs::write(dir.join("g/t/src/extra.rs"), "extra").unwrap();
+        let files = ["g/t/src/lib.rs", "g/t/src/extra.rs"];
+        solutions.commit_files(&files, "first").unwrap();
+
+        fs::remove_file(dir.join("g/t/src/extra.rs")).unwrap();
+        fs::write(dir.join("g/t/src/lib.rs"), "new lib").unwrap();
+        solutions.commit_files(&files, "second").unwrap();
+
+        let head = solutions.repo.head().unwrap().peel_to_commit().unwrap();
+        assert_eq!(head.message(), Some("second"));
+        let tree = head.tree().unwrap();
+        assert!(tree.get_path(Path::new("g/t/src/extra.rs")).is_err());
+        assert!(tree.get_path(Path::new("g/t/src/lib.rs")).is_ok());
+        assert!(solutions.commit_files(&files, "third").is_err());
+        let branch = solutions
+            .repo
+            .head()
+            .unwrap()
+            .shorthand()
+            .unwrap()
+            .to_string();
+        assert_eq!(
+            solutions
+                .files_on_branch(&branch, Path::new("g/t"))
+                .unwrap(),
+            [Path::new("src/lib.rs")]
+        );
+        assert!(solutions
+            .files_on_branch(&branch, Path::new("g/u"))
+            .unwrap()
+            .is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,18 +1,22 @@
 use super::command::Command;
+use std::time::Duration;
 
 #[derive(Debug)]
 pub struct Step {
-    #[allow(unused)]
     name: String,
     commands: Vec<Command>,
+    timeout: Option<Duration>,
 }
 
 impl Step {
-    pub fn new(name: String, commands: Vec<Command>) -> Self {
-        Self { name, commands }
+    pub fn new(name: String, commands: Vec<Command>, timeout: Option<Duration>) -> Self {
+        Self {
+            name,
+            commands,
+            timeout,
+        }
     }
 
-    #[allow(unused)]
     pub fn name(&self) -> &str {
         &self.name
     }
@@ -20,4 +24,9 @@ impl Step {
     pub fn commands(&self) -> &[Command] {
         self.commands.as_slice()
     }
+
+    /// Timeout for all the commands of the step together.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
 }
@@ -1,54 +1,34 @@
-use super::{command::Command, context::CommandContext};
+use super::{
+    command::{Command, ExpectedStatus, RunCommand},
+    context::{CommandContext, Echo},
+    overlay::TestOverlay,
+};
+use crate::{
+    linting::{collections, items, lint_user_files, no_std, unsafe_code},
+    testing::{
+        libtest::{humanize, parse_tests, HumanEcho},
+        result::CommandResult,
+    },
+    util::process_group,
+};
 use anyhow::{bail, Context, Result};
 use std::{
-    fs::File,
-    io::{BufRead, BufReader},
-    process,
+    io::{self, Read, Write},
+    process::{self, Stdio},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
 };
 
-const FORBID_UNSAFE_LINE: &str = "#![forbid(unsafe_code)]";
-const FORBID_STD_LINE: &str = "#![no_std]";
-const FORBID_COLLECTIONS_PATTERNS: [&str; 8] = [
-    "BTreeMap",
-    "BTreeSet",
-    "HashMap",
-    "HashSet",
-    "Vec",
-    "VecDeque",
-    "LinkedList",
-    "BinaryHeap",
-];
-
-macro_rules! launch {
-    ($toolchain: expr, $command: expr, $context: expr) => {{
-        let toolchain_shell_line = $toolchain.get_shell_line()?;
-        let command_shell_line = $command.get_shell_line()?;
-        let toolchain_shell_iter = toolchain_shell_line.split(' ');
-        let command_shell_iter = command_shell_line.split(' ');
-        let mut iter = toolchain_shell_iter.chain(command_shell_iter);
-        let mut cmd = if let Some(program) = iter.next() {
-            let mut cmd = process::Command::new(program);
-            cmd.current_dir($context.get_workdir());
-            cmd
-        } else {
-            bail!("toolchain and command are empty")
-        };
-        while let Some(arg) = iter.next() {
-            cmd.arg(arg);
-        }
-        if cmd.status().context("command failed")?.success() {
-            Ok(())
-        } else {
-            bail!("command failed")
-        }
-    }};
-}
+const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+const LIBTEST_JSON_ARGS: [&str; 4] = ["-Z", "unstable-options", "--format", "json"];
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub enum Toolchain {
     Empty,
     Stable,
     Nightly,
+    /// A toolchain pinned to a date or a version, e.g. `nightly-2024-05-01` or `1.75`.
+    Pinned(String),
 }
 
 impl Toolchain {
@@ -57,160 +37,342 @@ impl Toolchain {
             "empty" => Self::Empty,
             "stable" => Self::Stable,
             "nightly" => Self::Nightly,
-            name => bail!("toolchain \"{name}\" is not supported"),
+            name if is_pinned(name) => Self::Pinned(name.to_string()),
+            name => bail!(
+                "toolchain \"{name}\" is not supported, expected \"stable\", \"nightly\", a dated one like \"nightly-2024-05-01\" or a version like \"1.75\""
+            ),
         })
     }
 
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Empty => "empty",
+            Self::Stable => "stable",
+            Self::Nightly => "nightly",
+            Self::Pinned(name) => name,
+        }
+    }
+
+    pub fn is_nightly(&self) -> bool {
+        match self {
+            Self::Nightly => true,
+            Self::Pinned(name) => name.starts_with("nightly"),
+            Self::Empty | Self::Stable => false,
+        }
+    }
+
     pub fn get_shell_line(&self) -> Result<String> {
         Ok(match self {
             Self::Empty => "".to_string(),
-            Self::Stable => "rustup run stable".to_string(),
-            Self::Nightly => "rustup run nightly".to_string(),
+            Self::Stable | Self::Nightly | Self::Pinned(_) => format!("rustup run {}", self.name()),
         })
     }
 
-    pub fn run_command(&self, command: &Command, context: &CommandContext) -> Result<()> {
-        match command {
-            Command::ForbidUnsafe => {
-                for file in context.get_user_files() {
-                    if let Some(line) = BufReader::new(File::open(file)?)
-                        .lines()
-                        .next()
-                        .transpose()?
-                    {
-                        if line != FORBID_UNSAFE_LINE {
-                            bail!(format!(
-                                "file {file:?} does not contain line '{FORBID_UNSAFE_LINE}'"
-                            ))
-                        }
-                    } else {
-                        // TODO: ForbidUnsafe shouldn't check whether file is empty
-                        bail!(format!("file {file:?} is empty"))
-                    }
-                }
-                Ok(())
-            }
-            Command::ForbidCollections => {
-                for file in context.get_user_files() {
-                    for line in BufReader::new(File::open(file)?).lines() {
-                        let line = line.context("error reading file line")?;
-                        for pattern in FORBID_COLLECTIONS_PATTERNS {
-                            if line.contains(pattern) {
-                                bail!(format!("file {file:?} contains line '{pattern}'"))
-                            }
-                        }
-                    }
-                }
-                Ok(())
-            }
-            Command::ForbidStd => {
-                for file in context.get_user_files() {
-                    if let Some(line) = BufReader::new(File::open(file)?)
-                        .lines()
-                        .next()
-                        .transpose()?
-                    {
-                        if line != FORBID_STD_LINE {
-                            bail!(format!(
-                                "file {file:?} does not contain line '{FORBID_STD_LINE}'"
-                            ))
-                        }
-                    } else {
-                        // TODO: ForbidStd shouldn't check whether file is empty
-                        bail!(format!("file {file:?} is empty"))
-                    }
-                }
-                Ok(())
+    /// Checks that the toolchain and the components are installed. The error tells
+    /// how to install the missing ones.
+    pub fn preflight(&self, components: &[String]) -> Result<()> {
+        if let Self::Empty = self {
+            return Ok(());
+        }
+        let name = self.name();
+        let install_command = if components.is_empty() {
+            format!("rustup toolchain install {name} --profile minimal")
+        } else {
+            format!(
+                "rustup toolchain install {name} --profile minimal --component {}",
+                components.join(",")
+            )
+        };
+        let host = parse_host(&rustup(&["show"])?)
+            .context("failed to find the host triple in the output of \"rustup show\"")?
+            .to_string();
+        if !is_installed(&rustup(&["toolchain", "list"])?, name, &host) {
+            bail!("toolchain \"{name}\" is not installed, install it with:\n  {install_command}")
+        }
+        let installed = rustup(&["component", "list", "--installed", "--toolchain", name])?;
+        let installed: Vec<_> = installed.lines().map(str::trim).collect();
+        let missing: Vec<_> = components
+            .iter()
+            .filter(|component| {
+                !installed.iter().any(|installed| {
+                    *installed == component.as_str() || *installed == format!("{component}-{host}")
+                })
+            })
+            .map(String::as_str)
+            .collect();
+        if !missing.is_empty() {
+            bail!(
+                "toolchain \"{name}\" has no {}, install with:\n  rustup component add --toolchain {name} {}",
+                missing.join(", "),
+                missing.join(" ")
+            )
+        }
+        Ok(())
+    }
+
+    pub fn run_command(
+        &self,
+        command: &Command,
+        context: &CommandContext,
+        timeout: Duration,
+    ) -> CommandResult {
+        let mut result = CommandResult::new(command.name());
+        let start = Instant::now();
+        let run = match command {
+            Command::ForbidUnsafe => lint_user_files(context, &mut result, unsafe_code::check),
+            Command::ForbidCollections => lint_user_files(context, &mut result, collections::check),
+            Command::ForbidStd => lint_user_files(context, &mut result, no_std::check),
+            Command::ForbidItems(patterns) => lint_user_files(context, &mut result, |source| {
+                items::check(source, patterns)
+            }),
+            Command::Run(run) => self.launch(run, context, timeout, &mut result),
+        };
+        if let Err(err) = run {
+            result.fail(format!("{err:#}"));
+        }
+        result.set_duration(start.elapsed());
+        result
+    }
+
+    fn launch(
+        &self,
+        run: &RunCommand,
+        context: &CommandContext,
+        timeout: Duration,
+        result: &mut CommandResult,
+    ) -> Result<()> {
+        let toolchain_shell_line = self.get_shell_line()?;
+        let libtest_json = context.get_libtest_json() && run.is_cargo_test();
+        let libtest_separator = match run.args().iter().any(|arg| arg == "--") {
+            true => None,
+            false => Some("--"),
+        };
+        let argv = toolchain_shell_line
+            .split(' ')
+            .filter(|arg| !arg.is_empty())
+            .chain(std::iter::once(run.program()))
+            .chain(run.args().iter().map(String::as_str))
+            .chain(
+                libtest_separator
+                    .into_iter()
+                    .chain(LIBTEST_JSON_ARGS)
+                    .filter(|_| libtest_json),
+            );
+        let mut cmd = match context.get_sandbox() {
+            Some(sandbox) => {
+                sandbox.command(context.get_workdir(), context.get_target_dir(), argv)?
             }
-            Command::CargoCompileTestMiniFrunk => {
-                // TODO: hardcoded, better to refactor all the code
-                if process::Command::new("cargo")
-                    .current_dir(context.get_workdir())
-                    .arg("test")
-                    .arg("--features")
-                    .arg("compilation-fail-generic")
-                    .status()
-                    .context("command failed")?
-                    .success()
-                {
-                    bail!("command failed")
-                }
-                if process::Command::new("cargo")
-                    .current_dir(context.get_workdir())
-                    .arg("test")
-                    .arg("--features")
-                    .arg("compilation-fail-labelled")
-                    .status()
-                    .context("command failed")?
-                    .success()
-                {
-                    bail!("command failed")
-                }
-                if process::Command::new("cargo")
-                    .current_dir(context.get_workdir())
-                    .arg("test")
-                    .arg("--features")
-                    .arg("compilation-fail-transmogrify")
-                    .status()
-                    .context("command failed")?
-                    .success()
-                {
-                    bail!("command failed")
-                }
-                println!("Compile tests passed, don't worry :)");
-                Ok(())
+            None => {
+                let mut argv = argv;
+                let mut cmd = process::Command::new(argv.next().unwrap());
+                cmd.args(argv);
+                cmd
             }
-            Command::CargoCompileTestOrm => {
-                // TODO: hardcoded, better to refactor all the code
-                if process::Command::new("cargo")
-                    .current_dir(context.get_workdir())
-                    .arg("test")
-                    .arg("--features")
-                    .arg("test-lifetimes-create")
-                    .status()
-                    .context("command failed")?
-                    .success()
-                {
-                    bail!("command failed")
-                }
-                if process::Command::new("cargo")
-                    .current_dir(context.get_workdir())
-                    .arg("test")
-                    .arg("--features")
-                    .arg("test-lifetimes-get")
-                    .status()
-                    .context("command failed")?
-                    .success()
-                {
-                    bail!("command failed")
-                }
-                println!("Compile tests passed, don't worry :)");
-                Ok(())
+        };
+        cmd.current_dir(context.get_workdir())
+            .envs(run.env())
+            .envs(
+                context
+                    .get_target_dir()
+                    .map(|target_dir| ("CARGO_TARGET_DIR", target_dir)),
+            )
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        // Removed when the command is finished.
+        let _overlay = match context.get_private_tests() {
+            Some(private_tests) if run.is_cargo_test() => Some(TestOverlay::apply(
+                private_tests,
+                &context.get_workdir().join("tests"),
+            )?),
+            _ => None,
+        };
+        let mut child = process_group::spawn(&mut cmd)
+            .with_context(|| format!("failed to launch \"{}\"", run.get_shell_line()))?;
+        let (stdout, stderr): (Box<dyn Write + Send>, Box<dyn Write + Send>) =
+            match context.get_echo() {
+                Echo::Inherit => (Box::new(io::stdout()), Box::new(io::stderr())),
+                Echo::Stderr => (Box::new(io::stderr()), Box::new(io::stderr())),
+                Echo::Quiet => (Box::new(io::sink()), Box::new(io::sink())),
+            };
+        let stdout: Box<dyn Write + Send> = match libtest_json {
+            true => Box::new(HumanEcho::new(stdout)),
+            false => stdout,
+        };
+        let stdout = capture(child.stdout.take().unwrap(), stdout);
+        let stderr = capture(child.stderr.take().unwrap(), stderr);
+
+        let deadline = Instant::now() + timeout;
+        let mut timed_out = false;
+        let status = loop {
+            if let Some(status) = child.try_wait().context("command failed")? {
+                break status;
             }
-            Command::CargoCompileTestSnapshot => {
-                // TODO: hardcoded, better to refactor all the code
-                if process::Command::new("cargo")
-                    .current_dir(context.get_workdir())
-                    .arg("test")
-                    .arg("--features")
-                    .arg("test-lifetime")
-                    .status()
-                    .context("command failed")?
-                    .success()
-                {
-                    bail!("command failed")
-                }
-                println!("Compile tests passed, don't worry :)");
-                Ok(())
+            if Instant::now() >= deadline {
+                process_group::kill(&child)?;
+                timed_out = true;
+                break child.wait().context("command failed")?;
             }
-            Command::CargoFmt
-            | Command::CargoClippy
-            | Command::CargoTest
-            | Command::CargoTestDebug
-            | Command::PythonTest
-            | Command::CargoMiriTest => {
-                launch!(self, command, context)
+            thread::sleep(WAIT_POLL_INTERVAL);
+        };
+        // Whatever the command has left running would keep the pipes open.
+        process_group::kill(&child)?;
+        let stdout = stdout.join().unwrap().context("failed to read stdout")?;
+        let stderr = stderr.join().unwrap().context("failed to read stderr")?;
+        if run.is_cargo_test() {
+            result.set_tests(parse_tests(&stdout, &stderr));
+        }
+        match libtest_json {
+            true => result.set_output(&humanize(&stdout), &stderr),
+            false => result.set_output(&stdout, &stderr),
+        }
+        result.set_exit_code(status.code());
+
+        if timed_out {
+            result.time_out(format!(
+                "timed out after {}",
+                humantime::format_duration(timeout)
+            ));
+            return Ok(());
+        }
+        let expected = run.expected_status();
+        let matches = match expected {
+            ExpectedStatus::Success => status.success(),
+            ExpectedStatus::Failure => !status.success(),
+            ExpectedStatus::Code(code) => status.code() == Some(code),
+        };
+        if !matches {
+            result.fail(format!("expected {expected:?}, got {status}"));
+        }
+        Ok(())
+    }
+}
+
+fn is_pinned(name: &str) -> bool {
+    let is_version = |version: &str| {
+        let parts: Vec<_> = version.split('.').collect();
+        (2..=3).contains(&parts.len())
+            && parts
+                .iter()
+                .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()))
+    };
+    let is_date = |date: &str| {
+        date.len() == 10
+            && date.chars().enumerate().all(|(i, c)| match i {
+                4 | 7 => c == '-',
+                _ => c.is_ascii_digit(),
+            })
+    };
+    match name.split_once('-') {
+        Some(("stable" | "beta" | "nightly", date)) => is_date(date),
+        _ => is_version(name),
+    }
+}
+
+fn parse_host(rustup_show: &str) -> Option<&str> {
+    rustup_show
+        .lines()
+        .find_map(|line| line.strip_prefix("Default host:"))
+        .map(str::trim)
+        .filter(|host| !host.is_empty())
+}
+
+/// Installed toolchains are listed with the host, e.g. `1.75-x86_64-unknown-linux-gnu`,
+/// and may be followed by `(default)`.
+fn is_installed(toolchain_list: &str, name: &str, host: &str) -> bool {
+    let expected = format!("{name}-{host}");
+    toolchain_list
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .any(|installed| installed == expected)
+}
+
+fn rustup(args: &[&str]) -> Result<String> {
+    let output = process::Command::new("rustup")
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .context("failed to launch rustup, install it from https://rustup.rs")?;
+    if !output.status.success() {
+        bail!(
+            "\"rustup {}\" failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Echoes everything read from the pipe and returns the whole output.
+fn capture<R: Read + Send + 'static>(
+    mut pipe: R,
+    mut echo: Box<dyn Write + Send>,
+) -> JoinHandle<io::Result<Vec<u8>>> {
+    thread::spawn(move || {
+        let mut output = Vec::new();
+        let mut buf = [0; 8192];
+        loop {
+            let len = pipe.read(&mut buf)?;
+            if len == 0 {
+                return Ok(output);
             }
+            echo.write_all(&buf[..len])?;
+            echo.flush()?;
+            output.extend_from_slice(&buf[..len]);
         }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOST: &str = "x86_64-unknown-linux-gnu";
+
+    #[test]
+    fn pinned_names() {
+        for name in [
+            "1.75",
+            "1.75.0",
+            "nightly-2024-05-01",
+            "stable-2024-05-01",
+            "beta-2024-05-01",
+        ] {
+            assert!(is_pinned(name), "{name}");
+        }
+        for name in [
+            "stable",
+            "nightly",
+            "1",
+            "1.75.0.1",
+            "1.x",
+            "1..75",
+            "nightly-2024-5-1",
+            "nightly-2024-05-01-x86_64-unknown-linux-gnu",
+            "custom-2024-05-01",
+        ] {
+            assert!(!is_pinned(name), "{name}");
+        }
+    }
+
+    #[test]
+    fn host_from_rustup_show() {
+        let output = "Default host: x86_64-unknown-linux-gnu\nrustup home:  /root/.rustup\n";
+        assert_eq!(parse_host(output), Some(HOST));
+        assert_eq!(parse_host("rustup home:  /root/.rustup\n"), None);
+    }
+
+    #[test]
+    fn installed_toolchains() {
+        let list = "stable-x86_64-unknown-linux-gnu (active, default)\n\
+                    nightly-2024-05-01-x86_64-unknown-linux-gnu\n\
+                    1.75-x86_64-unknown-linux-gnu\n";
+        assert!(is_installed(list, "stable", HOST));
+        assert!(is_installed(list, "nightly-2024-05-01", HOST));
+        assert!(is_installed(list, "1.75", HOST));
+        // Only a dated nightly is installed.
+        assert!(!is_installed(list, "nightly", HOST));
+        assert!(!is_installed(list, "1.7", HOST));
+        assert!(!is_installed(list, "stable", "aarch64-apple-darwin"));
     }
 }
@@ -0,0 +1,414 @@
+use crate::{
+    checking::check_config::check_config,
+    compose::config::Config,
+    repository::{
+        problem::DEFAULT_YML_NAME,
+        repo::{Repository, COMPOSE_CONFIG, PROBLEMS_FOLDER},
+    },
+};
+use anyhow::{bail, Context, Result};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use toml_edit::{DocumentMut, Value};
+
+const CARGO_TOML: &str = "Cargo.toml";
+/// Prefix of the compose markers of the sources, put together at runtime: the literal
+/// markers would be hidden when rover itself is composed.
+const MARKER: &str = concat!("compose", "::");
+
+#[derive(Clone, Copy)]
+pub enum Template {
+    Lib,
+    Bin,
+    ProcMacro,
+}
+
+impl Template {
+    pub fn from_name(name: &str) -> Result<Self> {
+        Ok(match name {
+            "lib" => Self::Lib,
+            "bin" => Self::Bin,
+            "proc-macro" => Self::ProcMacro,
+            name => bail!("template \"{name}\" is not supported"),
+        })
+    }
+
+    fn source_file(&self) -> &str {
+        match self {
+            Self::Lib | Self::ProcMacro => "src/lib.rs",
+            Self::Bin => "src/main.rs",
+        }
+    }
+
+    fn files(&self, title: &str) -> Vec<(&str, String)> {
+        let krate = title.replace('-', "_");
+        let (manifest_tail, source, tests) = match self {
+            Self::Lib => (
+                String::new(),
+                LIB_SOURCE.replace("{marker}", MARKER),
+                LIB_TESTS.replace("{crate}", &krate),
+            ),
+            Self::Bin => (
+                String::new(),
+                BIN_SOURCE.replace("{marker}", MARKER),
+                BIN_TESTS.replace("{title}", title),
+            ),
+            Self::ProcMacro => (
+                "\n[lib]\nproc-macro = true\n".to_string(),
+                PROC_MACRO_SOURCE.replace("{marker}", MARKER),
+                PROC_MACRO_TESTS.replace("{crate}", &krate),
+            ),
+        };
+        vec![
+            (
+                CARGO_TOML,
+                format!(
+                    "[package]\nname = \"{title}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n{manifest_tail}"
+                ),
+            ),
+            ("README.md", README.replace("{title}", title)),
+            (self.source_file(), source),
+            ("tests/tests.rs", tests),
+            (
+                DEFAULT_YML_NAME,
+                CONFIG.replace("{source}", self.source_file()),
+            ),
+        ]
+    }
+}
+
+/// Creates `problems/GROUP/TITLE` from the template, adds it to the workspace and to
+/// `.compose.yml` if there is one, and validates its config. The manifests are changed
+/// only after the new problem is validated.
+pub fn new_problem(path: &Path, group: &str, title: &str, template: Template) -> Result<()> {
+    for name in [group, title] {
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+        {
+            bail!("\"{name}\" may contain only lowercase letters, digits, \"-\" and \"_\"")
+        }
+    }
+    let repository = Repository::from_path(path)?;
+    let relative_path = PathBuf::from(PROBLEMS_FOLDER).join(group).join(title);
+    let problem_path = repository.get_path().join(&relative_path);
+    if problem_path.exists() {
+        bail!("problem {group}/{title} already exists")
+    }
+
+    let manifest_path = repository.get_path().join(CARGO_TOML);
+    let member = relative_path.to_str().context("non-utf-8 path")?;
+    let manifest = add_to_workspace(&read_file(&manifest_path)?, member)
+        .with_context(|| format!("failed to add {member} to {manifest_path:?}"))?;
+    let compose_path = repository.get_path().join(COMPOSE_CONFIG);
+    let compose_config = if compose_path.exists() {
+        let problem = format!("{group}/{title}");
+        add_to_compose_config(&read_file(&compose_path)?, &problem)
+            .with_context(|| format!("failed to add {problem} to {compose_path:?}"))?
+    } else {
+        None
+    };
+
+    let created = write_problem(&problem_path, template.files(title))
+        .and_then(|()| check_config(&problem_path, false));
+    if let Err(err) = created {
+        fs::remove_dir_all(&problem_path).ok();
+        return Err(err);
+    }
+
+    if let Some(manifest) = manifest {
+        fs::write(&manifest_path, manifest)
+            .with_context(|| format!("failed to write {manifest_path:?}"))?;
+        eprintln!("added {member} to {CARGO_TOML}");
+    }
+    if let Some(compose_config) = compose_config {
+        fs::write(&compose_path, compose_config)
+            .with_context(|| format!("failed to write {compose_path:?}"))?;
+        eprintln!("added {group}/{title} to {COMPOSE_CONFIG}");
+    }
+    Ok(())
+}
+
+fn read_file(path: &Path) -> Result<String> {
+    fs::read_to_string(path).with_context(|| format!("failed to read {path:?}"))
+}
+
+fn write_problem(problem_path: &Path, files: Vec<(&str, String)>) -> Result<()> {
+    for (file, content) in files {
+        let file = problem_path.join(file);
+        fs::create_dir_all(file.parent().unwrap())
+            .with_context(|| format!("failed to create {:?}", file.parent().unwrap()))?;
+        fs::write(&file, content).with_context(|| format!("failed to write {file:?}"))?;
+        eprintln!("created {}", file.display());
+    }
+    Ok(())
+}
+
+/// Adds the member after the other problems keeping the formatting and the comments of
+/// the manifest. Returns `None` if it's already a member.
+fn add_to_workspace(manifest: &str, member: &str) -> Result<Option<String>> {
+    let mut manifest = manifest.parse::<DocumentMut>()?;
+    let members = manifest
+        .get_mut("workspace")
+        .and_then(|workspace| workspace.get_mut("members"))
+        .and_then(|members| members.as_array_mut())
+        .context("Cargo.toml has no \"workspace.members\" array")?;
+    if members.iter().any(|value| value.as_str() == Some(member)) {
+        return Ok(None);
+    }
+    let problems_prefix = format!("{PROBLEMS_FOLDER}/");
+    let index = members
+        .iter()
+        .enumerate()
+        .filter(|(_, value)| {
+            value
+                .as_str()
+                .is_some_and(|value| value.starts_with(&problems_prefix))
+        })
+        .map(|(last, _)| last + 1)
+        .last()
+        .unwrap_or(members.len());
+    members.insert(index, member);
+    // A comment after the previous member is in the prefix of the next one, so the new
+    // member takes it over.
+    let prefix = members
+        .get_mut(index + 1)
+        .map(Value::decor_mut)
+        .and_then(|decor| decor.prefix().cloned())
+        .unwrap_or_else(|| "\n    ".into());
+    if let Some(next) = members.get_mut(index + 1) {
+        next.decor_mut().set_prefix("\n    ");
+    }
+    members
+        .get_mut(index)
+        .map(Value::decor_mut)
+        .unwrap()
+        .set_prefix(prefix);
+    Ok(Some(manifest.to_string()))
+}
+
+/// Inserts the problem into the block list of `problems` after the other problems of
+/// its group, editing the text so that the comments and the rest of the file are kept.
+/// Returns `None` if the problem is already listed.
+fn add_to_compose_config(config: &str, problem: &str) -> Result<Option<String>> {
+    let parsed: Config = serde_yaml::from_str(config).context("invalid config")?;
+    if parsed
+        .get_problems()
+        .iter()
+        .any(|other| other == Path::new(problem))
+    {
+        return Ok(None);
+    }
+
+    let lines = config.lines().collect::<Vec<_>>();
+    let key = lines
+        .iter()
+        .position(|line| line.trim_end() == "problems:")
+        .context("\"problems\" is not a block list, add the problem manually")?;
+    // (line index, indentation, problem) of every item of the list.
+    let mut items = vec![];
+    for (index, line) in lines.iter().enumerate().skip(key + 1) {
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let Some(item) = trimmed.strip_prefix("- ") else {
+            break;
+        };
+        let indent = &line[..line.len() - trimmed.len()];
+        let item = item.split(" #").next().unwrap().trim();
+        items.push((index, indent, item));
+    }
+
+    let group = Path::new(problem).parent();
+    let (after, indent) = items
+        .iter()
+        .rev()
+        .find(|(_, _, item)| Path::new(item).parent() == group)
+        .or(items.last())
+        .map_or((key, "  "), |&(index, indent, _)| (index, indent));
+
+    let mut result = lines[..=after].join("\n");
+    result.push_str(&format!("\n{indent}- {problem}\n"));
+    for line in &lines[after + 1..] {
+        result.push_str(line);
+        result.push('\n');
+    }
+    if !config.ends_with('\n') {
+        result.pop();
+    }
+
+    let parsed: Config = serde_yaml::from_str(&result).context("failed to edit config")?;
+    if !parsed
+        .get_problems()
+        .iter()
+        .any(|other| other == Path::new(problem))
+    {
+        bail!("failed to edit config, add the problem manually")
+    }
+    Ok(Some(result))
+}
+
+const README: &str = "# {title}
+
+## Task
+
+Describe the task here.
+";
+
+const CONFIG: &str = "toolchain: stable
+allowed-patterns:
+  - {source}
+steps:
+  linters:
+    - forbid-unsafe
+    - cargo-fmt
+    - cargo-clippy
+  testing:
+    - cargo-test
+";
+
+const LIB_SOURCE: &str = "#![forbid(unsafe_code)]
+
+pub fn answer() -> i32 {
+    // {marker}begin_private(unimplemented)
+    42
+    // {marker}end_private
+}
+";
+
+const LIB_TESTS: &str = "use {crate}::answer;
+
+#[test]
+fn it_works() {
+    assert_eq!(answer(), 42);
+}
+";
+
+const BIN_SOURCE: &str = "#![forbid(unsafe_code)]
+
+fn main() {
+    // {marker}begin_private(unimplemented)
+    println!(\"42\");
+    // {marker}end_private
+}
+";
+
+const BIN_TESTS: &str = "use std::process::Command;
+
+#[test]
+fn it_works() {
+    let output = Command::new(env!(\"CARGO_BIN_EXE_{title}\"))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), \"42\\n\");
+}
+";
+
+const PROC_MACRO_SOURCE: &str = "#![forbid(unsafe_code)]
+
+use proc_macro::TokenStream;
+
+#[proc_macro]
+pub fn answer(_input: TokenStream) -> TokenStream {
+    // {marker}begin_private(unimplemented)
+    \"42\".parse().unwrap()
+    // {marker}end_private
+}
+";
+
+const PROC_MACRO_TESTS: &str = "use {crate}::answer;
+
+#[test]
+fn it_works() {
+    assert_eq!(answer!(), 42);
+}
+";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn templates_have_markers() {
+        for template in [Template::Lib, Template::Bin, Template::ProcMacro] {
+            let files = template.files("answer");
+            let (_, source) = files
+                .iter()
+                .find(|(file, _)| *file == template.source_file())
+                .unwrap();
+            assert!(source.contains(&format!("// {MARKER}begin_private(unimplemented)\n")));
+            assert!(source.contains(&format!("// {MARKER}end_private\n")));
+        }
+    }
+
+    const COMPOSE_CONFIG: &str = "# The course.
+problems:
+  # Basics.
+  - basics/add   # first
+  - basics/sum
+  - async/chat
+tools: []
+copy: []
+skip-entries: []
+add-to-toml: []
+do-not-delete: []
+";
+
+    #[test]
+    fn compose_config_keeps_comments() {
+        let config = add_to_compose_config(COMPOSE_CONFIG, "basics/mul")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            config,
+            COMPOSE_CONFIG.replace("  - basics/sum\n", "  - basics/sum\n  - basics/mul\n")
+        );
+    }
+
+    #[test]
+    fn compose_config_new_group() {
+        let config = add_to_compose_config(COMPOSE_CONFIG, "tools/grep")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            config,
+            COMPOSE_CONFIG.replace("  - async/chat\n", "  - async/chat\n  - tools/grep\n")
+        );
+    }
+
+    #[test]
+    fn compose_config_listed_problem() {
+        assert!(add_to_compose_config(COMPOSE_CONFIG, "basics/add")
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn compose_config_flow_list() {
+        let config = COMPOSE_CONFIG.replace(
+            "problems:\n  # Basics.\n  - basics/add   # first\n  - basics/sum\n  - async/chat\n",
+            "problems: [basics/add]\n",
+        );
+        assert!(add_to_compose_config(&config, "basics/mul").is_err());
+    }
+
+    #[test]
+    fn workspace_member() {
+        let manifest = "[workspace]\nmembers = [\n    \"problems/basics/add\", # first\n    \"tools/rover\",\n]\n";
+        let manifest = add_to_workspace(manifest, "problems/basics/sum")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            manifest,
+            "[workspace]\nmembers = [\n    \"problems/basics/add\", # first\n    \"problems/basics/sum\",\n    \"tools/rover\",\n]\n"
+        );
+        assert!(add_to_workspace(&manifest, "problems/basics/sum")
+            .unwrap()
+            .is_none());
+    }
+}
@@ -1,6 +1,10 @@
-use crate::{launch_git, repository::repo::Repository};
-use anyhow::{bail, Result};
+use crate::{
+    launch_git,
+    repository::{problem::Problem, repo::Repository, solutions::SolutionsRepo},
+};
+use anyhow::{bail, Context, Result};
 use std::{
+    fs,
     path::{Path, PathBuf},
     process,
 };
@@ -9,6 +13,7 @@ pub fn submit_problem(
     problem_path: &Path,
     message: &str,
     solutions_repo: Option<PathBuf>,
+    dry_run: bool,
 ) -> Result<()> {
     let repository = Repository::from_path(problem_path)?;
     let problem = repository.problem_from_path(problem_path)?;
@@ -16,16 +21,18 @@ pub fn submit_problem(
         Some(path) => path,
         None => repository.solutions_repo()?,
     };
-    problem.move_solution_files_to(&solutions_repo)?;
-    if !launch_git!(&solutions_repo, "add", ".") {
-        bail!("git add failed");
-    }
-    if !launch_git!(&solutions_repo, "commit", "-m", message) {
-        bail!("git commit failed: either no changes since the last commit or git failed")
+    let solutions_repo = SolutionsRepo::open(&solutions_repo)?;
+    solutions_repo.ensure_ready()?;
+    if dry_run {
+        return preview_submit(&problem, &solutions_repo);
     }
-    if !launch_git!(&solutions_repo, "push")
+    let files = problem.move_solution_files_to(&solutions_repo)?;
+    solutions_repo.commit_files(&files, message)?;
+    // Pushing is left to git itself, so the credentials are handled as usual.
+    let workdir = solutions_repo.workdir();
+    if !launch_git!(workdir, "push")
         && !launch_git!(
-            &solutions_repo,
+            workdir,
             "push",
             "--set-upstream",
             "origin",
@@ -36,3 +43,52 @@ pub fn submit_problem(
     }
     Ok(())
 }
+
+/// Prints the files that would be submitted and their diff against the branch.
+fn preview_submit(problem: &Problem, solutions_repo: &SolutionsRepo) -> Result<()> {
+    let config = problem.config()?;
+    let branch = problem.branch_name();
+    println!(
+        "Files to submit to branch \"{branch}\" of {:?}:",
+        solutions_repo.workdir()
+    );
+    let mut diffs = String::new();
+    for (file, solution_file) in config
+        .get_absolute_user_files()
+        .iter()
+        .zip(problem.solution_files(&config))
+    {
+        let new = fs::read(file).with_context(|| format!("failed to read {file:?}"))?;
+        let old = solutions_repo.file_on_branch(&branch, &solution_file)?;
+        let status = match &old {
+            None => "new",
+            Some(old) if *old == new => "unchanged",
+            Some(_) => "modified",
+        };
+        println!("  {status:9} {}", solution_file.display());
+        if old.as_ref() != Some(&new) {
+            let mut patch = git2::Patch::from_buffers(
+                old.as_deref().unwrap_or_default(),
+                old.as_ref().map(|_| solution_file.as_path()),
+                &new,
+                Some(&solution_file),
+                None,
+            )
+            .context("failed to diff files")?;
+            diffs += patch.to_buf()?.as_str().unwrap_or("binary files differ\n");
+        }
+    }
+    for solution_file in problem.deleted_solution_files(&config, solutions_repo)? {
+        println!("  {:9} {}", "deleted", solution_file.display());
+        let old = solutions_repo
+            .file_on_branch(&branch, &solution_file)?
+            .unwrap_or_default();
+        let mut patch = git2::Patch::from_buffers(&old, Some(&solution_file), &[], None, None)
+            .context("failed to diff files")?;
+        diffs += patch.to_buf()?.as_str().unwrap_or("binary files differ\n");
+    }
+    if !diffs.is_empty() {
+        print!("\n{diffs}");
+    }
+    Ok(())
+}
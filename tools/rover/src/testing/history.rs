@@ -0,0 +1,67 @@
+use super::result::{Outcome, TestReport};
+use crate::repository::problem::Problem;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Results of local runs are kept in the target folder of the workspace, so git ignores them.
+const HISTORY_FOLDER: &str = "target/rover/history";
+
+/// The outcome of the last local `rover test` of a problem.
+#[derive(Serialize, Deserialize)]
+pub struct LastRun {
+    outcome: Outcome,
+    finished_at: u64,
+}
+
+impl LastRun {
+    pub fn save(problem: &Problem, report: &TestReport) -> Result<()> {
+        let last_run = Self {
+            outcome: report.outcome(),
+            finished_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .context("system time is before unix epoch")?
+                .as_secs(),
+        };
+        let path = Self::path(problem);
+        fs::create_dir_all(path.parent().unwrap())
+            .with_context(|| format!("failed to create {:?}", path.parent().unwrap()))?;
+        let json = serde_json::to_string(&last_run).context("failed to serialize last run")?;
+        fs::write(&path, json).with_context(|| format!("failed to write {path:?}"))
+    }
+
+    /// Returns `None` if the problem was never tested locally.
+    pub fn load(problem: &Problem) -> Result<Option<Self>> {
+        let path = Self::path(problem);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let json = fs::read_to_string(&path).with_context(|| format!("failed to read {path:?}"))?;
+        serde_json::from_str(&json)
+            .map(Some)
+            .with_context(|| format!("invalid last run in {path:?}"))
+    }
+
+    pub fn outcome(&self) -> Outcome {
+        self.outcome
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        let finished_at = UNIX_EPOCH + Duration::from_secs(self.finished_at);
+        SystemTime::now()
+            .duration_since(finished_at)
+            .unwrap_or(Duration::ZERO)
+    }
+
+    fn path(problem: &Problem) -> PathBuf {
+        problem
+            .repository_path()
+            .join(HISTORY_FOLDER)
+            .join(problem.group())
+            .join(problem.title() + ".json")
+    }
+}
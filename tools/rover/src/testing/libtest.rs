@@ -0,0 +1,354 @@
+use super::result::{Outcome, TestCase};
+use serde::Deserialize;
+use std::io::{self, Write};
+
+const DOC_TESTS: &str = "doc-tests";
+
+/// An event of `--format json` of libtest, only the needed fields.
+#[derive(Deserialize)]
+struct Event {
+    #[serde(rename = "type")]
+    kind: String,
+    event: String,
+    name: Option<String>,
+    test_count: Option<u64>,
+    stdout: Option<String>,
+    passed: Option<u64>,
+    failed: Option<u64>,
+    ignored: Option<u64>,
+    exec_time: Option<f64>,
+}
+
+/// Extracts the outcomes of single tests from the output of `cargo test`. The output of
+/// libtest may be both human-readable and JSON.
+///
+/// Cargo prints which binary it runs to stderr and libtest prints the tests to stdout,
+/// so the binaries are matched with the runs of tests by their order.
+pub fn parse_tests(stdout: &[u8], stderr: &[u8]) -> Vec<TestCase> {
+    let stderr = String::from_utf8_lossy(stderr);
+    let binaries: Vec<_> = stderr
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            if line.starts_with("Doc-tests ") {
+                return Some(DOC_TESTS.to_string());
+            }
+            let binary = line.strip_prefix("Running ")?;
+            let binary = binary.strip_prefix("unittests ").unwrap_or(binary);
+            let binary = binary.split(" (").next().unwrap_or(binary);
+            Some(binary.to_string())
+        })
+        .collect();
+
+    let stdout = String::from_utf8_lossy(stdout);
+    let mut tests = Vec::new();
+    let mut run = None;
+    // The output of failed tests is printed between `failures:` and `test result:`,
+    // it may contain anything.
+    let mut in_failures = false;
+    for line in stdout.lines() {
+        if line == "failures:" {
+            in_failures = true;
+        } else if line.starts_with("test result: ") {
+            in_failures = false;
+        }
+        let (started, test) = match line.starts_with('{') {
+            true => parse_json_line(line),
+            false if in_failures => continue,
+            false => parse_human_line(line),
+        };
+        if started {
+            run = Some(run.map_or(0, |run| run + 1));
+        }
+        if let Some((name, outcome)) = test {
+            let binary = run
+                .and_then(|run| binaries.get(run))
+                .map_or("unknown", String::as_str);
+            tests.push(TestCase::new(binary.to_string(), name, outcome));
+        }
+    }
+    tests
+}
+
+/// Returns whether a run of a test binary starts on the line and the test on it.
+fn parse_human_line(line: &str) -> (bool, Option<(String, Outcome)>) {
+    if line.starts_with("running ") && line.ends_with(" tests") || line == "running 1 test" {
+        return (true, None);
+    }
+    let test = line
+        .strip_prefix("test ")
+        .and_then(|line| line.rsplit_once(" ... "))
+        .and_then(|(name, result)| {
+            let outcome = match result {
+                "ok" => Outcome::Passed,
+                "FAILED" => Outcome::Failed,
+                result if result.starts_with("ignored") => Outcome::Skipped,
+                _ => return None,
+            };
+            Some((name.to_string(), outcome))
+        });
+    (false, test)
+}
+
+fn parse_json_line(line: &str) -> (bool, Option<(String, Outcome)>) {
+    let Ok(event) = serde_json::from_str::<Event>(line) else {
+        return (false, None);
+    };
+    match (event.kind.as_str(), event.event.as_str()) {
+        ("suite", "started") => (true, None),
+        ("test", event_name) => {
+            let outcome = match event_name {
+                "ok" => Outcome::Passed,
+                "failed" | "timeout" => Outcome::Failed,
+                "ignored" => Outcome::Skipped,
+                _ => return (false, None),
+            };
+            (false, event.name.map(|name| (name, outcome)))
+        }
+        _ => (false, None),
+    }
+}
+
+/// Echoes the JSON output of libtest the way the human-readable one looks, so the JSON
+/// rover asks for on nightly is not shown to the students. Other lines pass as they are.
+pub struct HumanEcho<W: Write> {
+    inner: W,
+    line: Vec<u8>,
+    /// The names and the output of the failed tests of the current run.
+    failures: Vec<(String, String)>,
+}
+
+impl<W: Write> HumanEcho<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            line: Vec::new(),
+            failures: Vec::new(),
+        }
+    }
+
+    fn write_line(&mut self) -> io::Result<()> {
+        let line = std::mem::take(&mut self.line);
+        let event = match line.first() {
+            Some(b'{') => serde_json::from_slice::<Event>(&line).ok(),
+            _ => None,
+        };
+        match event {
+            Some(event) => {
+                let text = self.render(event);
+                self.inner.write_all(text.as_bytes())
+            }
+            None => self.inner.write_all(&line),
+        }
+    }
+
+    fn render(&mut self, event: Event) -> String {
+        let name = event.name.unwrap_or_default();
+        match (event.kind.as_str(), event.event.as_str()) {
+            ("suite", "started") => {
+                self.failures.clear();
+                let count = event.test_count.unwrap_or_default();
+                let plural = if count == 1 { "" } else { "s" };
+                format!("\nrunning {count} test{plural}\n")
+            }
+            ("test", "ok") => format!("test {name} ... ok\n"),
+            ("test", "ignored") => format!("test {name} ... ignored\n"),
+            ("test", "failed" | "timeout") => {
+                let text = format!("test {name} ... FAILED\n");
+                self.failures.push((name, event.stdout.unwrap_or_default()));
+                text
+            }
+            ("suite", result @ ("ok" | "failed")) => {
+                let mut text = String::new();
+                if !self.failures.is_empty() {
+                    text += "\nfailures:\n";
+                    for (name, stdout) in &self.failures {
+                        text += &format!("\n---- {name} stdout ----\n{stdout}");
+                        if !stdout.ends_with('\n') {
+                            text.push('\n');
+                        }
+                    }
+                    text += "\nfailures:\n";
+                    for (name, _) in &self.failures {
+                        text += &format!("    {name}\n");
+                    }
+                }
+                text += &format!(
+                    "\ntest result: {}. {} passed; {} failed; {} ignored; finished in {:.2}s\n\n",
+                    if result == "ok" { "ok" } else { "FAILED" },
+                    event.passed.unwrap_or_default(),
+                    event.failed.unwrap_or_default(),
+                    event.ignored.unwrap_or_default(),
+                    event.exec_time.unwrap_or_default()
+                );
+                text
+            }
+            _ => String::new(),
+        }
+    }
+}
+
+impl<W: Write> Write for HumanEcho<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for &byte in buf {
+            self.line.push(byte);
+            if byte == b'\n' {
+                self.write_line()?;
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> Drop for HumanEcho<W> {
+    fn drop(&mut self) {
+        if !self.line.is_empty() {
+            let _ = self.write_line();
+        }
+        let _ = self.inner.flush();
+    }
+}
+
+/// The JSON output of libtest rendered as the human-readable one.
+pub fn humanize(stdout: &[u8]) -> Vec<u8> {
+    let mut output = Vec::new();
+    let mut echo = HumanEcho::new(&mut output);
+    let _ = echo.write_all(stdout);
+    drop(echo);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STDERR: &str = "   Compiling sum v0.1.0
+     Running unittests src/lib.rs (target/debug/deps/sum-0123)
+     Running tests/tests.rs (target/debug/deps/tests-4567)
+   Doc-tests sum
+";
+
+    fn case(binary: &str, name: &str, outcome: Outcome) -> (String, String, Outcome) {
+        (binary.to_string(), name.to_string(), outcome)
+    }
+
+    fn parse(stdout: &str) -> Vec<(String, String, Outcome)> {
+        parse_tests(stdout.as_bytes(), STDERR.as_bytes())
+            .into_iter()
+            .map(|test| {
+                (
+                    test.binary().to_string(),
+                    test.name().to_string(),
+                    test.outcome(),
+                )
+            })
+            .collect()
+    }
+
+    fn expected() -> Vec<(String, String, Outcome)> {
+        vec![
+            case("src/lib.rs", "tests::unit", Outcome::Passed),
+            case("tests/tests.rs", "stress", Outcome::Failed),
+            case("tests/tests.rs", "slow", Outcome::Skipped),
+            case("doc-tests", "src/lib.rs - sum (line 3)", Outcome::Passed),
+        ]
+    }
+
+    #[test]
+    fn human_format() {
+        let stdout = "
+running 1 test
+test tests::unit ... ok
+
+test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.00s
+
+
+running 2 tests
+test stress ... FAILED
+test slow ... ignored, too slow
+
+failures:
+
+---- stress stdout ----
+test stress ... ok
+
+failures:
+    stress
+
+test result: FAILED. 0 passed; 1 failed; 1 ignored; 0 measured; 0 filtered out; finished in 0.00s
+
+
+running 1 test
+test src/lib.rs - sum (line 3) ... ok
+";
+        assert_eq!(parse(stdout), expected());
+    }
+
+    const JSON_STDOUT: &str = r#"{ "type": "suite", "event": "started", "test_count": 1 }
+{ "type": "test", "event": "started", "name": "tests::unit" }
+{ "type": "test", "name": "tests::unit", "event": "ok" }
+{ "type": "suite", "event": "ok", "passed": 1, "failed": 0, "ignored": 0, "measured": 0, "filtered_out": 0, "exec_time": 0.001 }
+{ "type": "suite", "event": "started", "test_count": 2 }
+{ "type": "test", "event": "started", "name": "stress" }
+{ "type": "test", "event": "started", "name": "slow" }
+{ "type": "test", "name": "stress", "event": "failed", "stdout": "panicked at tests/tests.rs:5:5\n" }
+{ "type": "test", "name": "slow", "event": "ignored", "message": "too slow" }
+{ "type": "suite", "event": "failed", "passed": 0, "failed": 1, "ignored": 1, "measured": 0, "filtered_out": 0, "exec_time": 0.25 }
+{ "type": "suite", "event": "started", "test_count": 1 }
+{ "type": "test", "event": "started", "name": "src/lib.rs - sum (line 3)" }
+{ "type": "test", "name": "src/lib.rs - sum (line 3)", "event": "ok" }
+{ "type": "suite", "event": "ok", "passed": 1, "failed": 0, "ignored": 0, "measured": 0, "filtered_out": 0, "exec_time": 0.1 }
+"#;
+
+    #[test]
+    fn json_format() {
+        assert_eq!(parse(JSON_STDOUT), expected());
+    }
+
+    #[test]
+    fn unknown_binary() {
+        let tests = parse_tests(b"test orphan ... ok\n", b"");
+        assert_eq!(tests[0].binary(), "unknown");
+    }
+
+    #[test]
+    fn json_echo() {
+        let stdout = String::from_utf8(humanize(
+            JSON_STDOUT
+                .lines()
+                .skip(4)
+                .take(6)
+                .collect::<Vec<_>>()
+                .join("\n")
+                .as_bytes(),
+        ))
+        .unwrap();
+        assert_eq!(
+            stdout,
+            "
+running 2 tests
+test stress ... FAILED
+test slow ... ignored
+
+failures:
+
+---- stress stdout ----
+panicked at tests/tests.rs:5:5
+
+failures:
+    stress
+
+test result: FAILED. 0 passed; 1 failed; 1 ignored; finished in 0.25s
+
+"
+        );
+        assert_eq!(
+            humanize(b"warning: unused\n{not json"),
+            b"warning: unused\n{not json"
+        );
+    }
+}
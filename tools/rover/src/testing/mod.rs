@@ -1,2 +1,8 @@
+pub mod history;
+pub mod libtest;
+pub mod output;
 pub mod report;
+pub mod result;
+pub mod score;
 pub mod test;
+pub mod watch;
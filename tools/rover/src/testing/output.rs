@@ -0,0 +1,337 @@
+use super::result::{Outcome, TestReport};
+use crate::{repository::context::Echo, util::table::format_table};
+use anyhow::{bail, Context, Result};
+use std::fmt::Write;
+
+pub enum OutputFormat {
+    Text,
+    Json,
+    Junit,
+}
+
+impl OutputFormat {
+    pub fn from_name(name: &str) -> Result<Self> {
+        Ok(match name {
+            "text" => Self::Text,
+            "json" => Self::Json,
+            "junit" => Self::Junit,
+            name => bail!("output format \"{name}\" is not supported"),
+        })
+    }
+
+    /// Machine-readable formats own stdout, so the output of commands goes to stderr.
+    pub fn echo(&self) -> Echo {
+        match self {
+            Self::Text => Echo::Inherit,
+            Self::Json | Self::Junit => Echo::Stderr,
+        }
+    }
+
+    /// Whether the results are a single document on stdout that nothing else may share.
+    pub fn owns_stdout(&self) -> bool {
+        match self {
+            Self::Text => false,
+            Self::Json | Self::Junit => true,
+        }
+    }
+
+    pub fn print(&self, report: &TestReport) -> Result<()> {
+        match self {
+            Self::Text => {
+                print_violations(report);
+                print_summary(report);
+                Ok(())
+            }
+            Self::Json => {
+                let json =
+                    serde_json::to_string_pretty(report).context("failed to serialize report")?;
+                println!("{json}");
+                Ok(())
+            }
+            Self::Junit => {
+                print!("{}", to_junit(report.problem(), &[report]));
+                Ok(())
+            }
+        }
+    }
+
+    /// Prints the reports of several problems: a table with a row per problem in text,
+    /// a list of reports in json and a test suite per problem step in junit.
+    pub fn print_all(&self, reports: &[TestReport]) -> Result<()> {
+        match self {
+            Self::Text => {
+                print_problems_summary(reports);
+                Ok(())
+            }
+            Self::Json => {
+                let json =
+                    serde_json::to_string_pretty(reports).context("failed to serialize reports")?;
+                println!("{json}");
+                Ok(())
+            }
+            Self::Junit => {
+                let reports: Vec<_> = reports.iter().collect();
+                print!("{}", to_junit("rover", &reports));
+                Ok(())
+            }
+        }
+    }
+}
+
+fn print_problems_summary(reports: &[TestReport]) {
+    // The scores are shown only if some problem gives partial credit.
+    let scored = reports.iter().any(|report| report.score().is_some());
+    let rows: Vec<_> = reports
+        .iter()
+        .map(|report| {
+            let mut row = vec![
+                report.problem().to_string(),
+                report.outcome().to_string(),
+                format!("{:.2?}", report.duration()),
+            ];
+            if scored {
+                row.push(report.score().map_or("-".to_string(), format_score));
+            }
+            row.push(match report.failures().next() {
+                Some((step, command)) => format!(
+                    "{}/{}: {}",
+                    step.name(),
+                    command.name(),
+                    command.failure_summary()
+                ),
+                None => String::new(),
+            });
+            row
+        })
+        .collect();
+    let header: &[&str] = match scored {
+        true => &["PROBLEM", "OUTCOME", "DURATION", "SCORE", "FIRST FAILURE"],
+        false => &["PROBLEM", "OUTCOME", "DURATION", "FIRST FAILURE"],
+    };
+    let failed = reports
+        .iter()
+        .filter(|report| report.outcome().is_failure())
+        .count();
+    eprintln!();
+    eprint!("{}", format_table(header, &rows));
+    eprintln!("\n{} passed, {failed} failed", reports.len() - failed);
+}
+
+fn print_violations(report: &TestReport) {
+    for step in report.steps() {
+        for command in step.commands() {
+            if command.violations().is_empty() {
+                continue;
+            }
+            eprintln!("{}/{} found violations:", step.name(), command.name());
+            for violation in command.violations() {
+                eprintln!("  {violation}");
+            }
+        }
+    }
+}
+
+fn print_summary(report: &TestReport) {
+    let rows: Vec<_> = report
+        .commands()
+        .map(|(step, command)| {
+            vec![
+                step.name().to_string(),
+                command.name().to_string(),
+                command.outcome().to_string(),
+                match command.outcome() {
+                    Outcome::Skipped => "-".to_string(),
+                    _ => format!("{:.2?}", command.duration()),
+                },
+            ]
+        })
+        .collect();
+    eprintln!();
+    eprint!(
+        "{}",
+        format_table(&["STEP", "COMMAND", "OUTCOME", "DURATION"], &rows)
+    );
+    eprintln!(
+        "\n{} passed, {} failed, {} timed out, {} skipped",
+        report.count(Outcome::Passed),
+        report.count(Outcome::Failed),
+        report.count(Outcome::TimedOut),
+        report.count(Outcome::Skipped)
+    );
+    if let Some(score) = report.score() {
+        let tests: Vec<_> = report
+            .commands()
+            .flat_map(|(_, command)| command.tests())
+            .filter(|test| test.outcome() != Outcome::Skipped)
+            .collect();
+        eprintln!(
+            "score {} with {} of {} tests passed",
+            format_score(score),
+            tests
+                .iter()
+                .filter(|test| test.outcome() == Outcome::Passed)
+                .count(),
+            tests.len()
+        );
+    }
+}
+
+fn format_score(score: f64) -> String {
+    format!("{:.1}%", score * 100.0)
+}
+
+fn to_junit(name: &str, reports: &[&TestReport]) -> String {
+    let mut xml = String::new();
+    let count = |outcome: Outcome| {
+        reports
+            .iter()
+            .map(|report| report.count(outcome))
+            .sum::<usize>()
+    };
+    writeln!(xml, r#"<?xml version="1.0" encoding="UTF-8"?>"#).unwrap();
+    writeln!(
+        xml,
+        r#"<testsuites name="{}" failures="{}" errors="{}">"#,
+        escape(name),
+        count(Outcome::Failed),
+        count(Outcome::TimedOut)
+    )
+    .unwrap();
+    for (report, step) in reports
+        .iter()
+        .flat_map(|report| report.steps().iter().map(move |step| (report, step)))
+    {
+        // A single problem keeps plain step names.
+        let suite = if reports.len() == 1 {
+            step.name().to_string()
+        } else {
+            format!("{}/{}", report.problem(), step.name())
+        };
+        let count = |outcome: Outcome| {
+            step.commands()
+                .iter()
+                .filter(|command| command.outcome() == outcome)
+                .count()
+        };
+        writeln!(
+            xml,
+            r#"  <testsuite name="{}" tests="{}" failures="{}" errors="{}" skipped="{}" time="{:.3}">"#,
+            escape(&suite),
+            step.commands().len(),
+            count(Outcome::Failed),
+            count(Outcome::TimedOut),
+            count(Outcome::Skipped),
+            step.duration().as_secs_f64()
+        )
+        .unwrap();
+        for command in step.commands() {
+            writeln!(
+                xml,
+                r#"    <testcase name="{}" classname="{}.{}" time="{:.3}">"#,
+                escape(command.name()),
+                escape(report.problem()),
+                escape(step.name()),
+                command.duration().as_secs_f64()
+            )
+            .unwrap();
+            if command.outcome() == Outcome::Skipped {
+                writeln!(xml, "      <skipped/>").unwrap();
+            }
+            if command.outcome().is_failure() {
+                let details = command
+                    .violations()
+                    .iter()
+                    .map(|violation| violation.to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                // A timeout is reported as an error, as the command hasn't finished.
+                let element = match command.outcome() {
+                    Outcome::TimedOut => "error",
+                    _ => "failure",
+                };
+                writeln!(
+                    xml,
+                    r#"      <{element} message="{}">{}</{element}>"#,
+                    escape(&command.failure_summary()),
+                    escape(&details)
+                )
+                .unwrap();
+            }
+            writeln!(
+                xml,
+                "      <system-out>{}</system-out>",
+                escape(command.stdout_tail())
+            )
+            .unwrap();
+            writeln!(
+                xml,
+                "      <system-err>{}</system-err>",
+                escape(command.stderr_tail())
+            )
+            .unwrap();
+            writeln!(xml, "    </testcase>").unwrap();
+        }
+        writeln!(xml, "  </testsuite>").unwrap();
+    }
+    writeln!(xml, "</testsuites>").unwrap();
+    xml
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c if c.is_control() && c != '\n' && c != '\t' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::result::{CommandResult, StepResult};
+
+    #[test]
+    fn junit_report() {
+        let mut passed = CommandResult::new("cargo-test".to_string());
+        passed.set_output(b"<ok> & \"done\"", b"warning: a < b");
+        let mut failed = CommandResult::new("cargo-clippy".to_string());
+        failed.fail("exit code 101".to_string());
+        let mut timed_out = CommandResult::new("cargo-miri-test".to_string());
+        timed_out.time_out("timed out after 1s".to_string());
+        let mut step = StepResult::new("test".to_string());
+        for command in [
+            passed,
+            failed,
+            timed_out,
+            CommandResult::skipped("python-test".to_string()),
+        ] {
+            step.push(command);
+        }
+        let mut report = TestReport::new("intro/sum".to_string());
+        report.push(step);
+
+        let xml = to_junit(report.problem(), &[&report]);
+        assert!(xml.contains(r#"<testsuites name="intro/sum" failures="1" errors="1">"#));
+        assert!(xml.contains(
+            r#"<testsuite name="test" tests="4" failures="1" errors="1" skipped="1" time="0.000">"#
+        ));
+        assert!(
+            xml.contains("<system-out>&lt;ok&gt; &amp; &quot;done&quot;</system-out>"),
+            "{xml}"
+        );
+        assert!(xml.contains("<system-err>warning: a &lt; b</system-err>"));
+        assert!(xml.contains(r#"<failure message="exit code 101"></failure>"#));
+        assert!(xml.contains(r#"<error message="timed out after 1s"></error>"#));
+        let skipped = xml.find(r#"<testcase name="python-test""#).unwrap();
+        assert!(xml[skipped..].contains("<skipped/>"));
+        assert_eq!(xml.matches("<skipped/>").count(), 1);
+    }
+}
@@ -0,0 +1,71 @@
+use super::result::TestReport;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{fs::File, path::Path};
+
+mod manytask;
+mod sink;
+mod webhook;
+
+/// A system that accepts the results of testing.
+pub trait Reporter {
+    /// `reports` are empty if testing failed before any problem was launched.
+    fn push_report(&self, reports: &[TestReport], failed: bool) -> Result<()>;
+
+    /// Whether the report is printed to stdout.
+    fn writes_to_stdout(&self) -> bool {
+        false
+    }
+}
+
+/// Settings of the report backends, read from the file passed with `--report-config`.
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ReportConfig {
+    #[serde(default)]
+    manytask: manytask::ManytaskConfig,
+    webhook: Option<webhook::WebhookConfig>,
+    #[serde(default)]
+    file: sink::SinkConfig,
+}
+
+impl ReportConfig {
+    pub fn from_yml(path: &Path) -> Result<Self> {
+        let file =
+            File::open(path).with_context(|| format!("no yml file with report config {path:?}"))?;
+        serde_yaml::from_reader(file).with_context(|| format!("invalid report config {path:?}"))
+    }
+}
+
+pub fn reporter_from_name(name: &str, config: ReportConfig) -> Result<Box<dyn Reporter>> {
+    Ok(match name {
+        "no-report" => Box::new(NoReport),
+        "manytask" => Box::new(manytask::Manytask::new(config.manytask)),
+        "webhook" => {
+            Box::new(webhook::Webhook::new(config.webhook.context(
+                "webhook report needs \"webhook\" section in report config",
+            )?)?)
+        }
+        "file" => Box::new(sink::Sink::new(config.file)),
+        name => bail!("report type \"{name}\" is not supported"),
+    })
+}
+
+/// What the webhook and the file sink write.
+#[derive(Serialize)]
+struct Payload<'a> {
+    failed: bool,
+    reports: &'a [TestReport],
+}
+
+struct NoReport;
+
+impl Reporter for NoReport {
+    fn push_report(&self, _: &[TestReport], failed: bool) -> Result<()> {
+        if failed {
+            bail!("testing failed")
+        } else {
+            Ok(())
+        }
+    }
+}
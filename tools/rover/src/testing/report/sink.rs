@@ -0,0 +1,58 @@
+use super::{Payload, Reporter};
+use crate::testing::result::TestReport;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+const STDOUT_PATH: &str = "-";
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct SinkConfig {
+    #[serde(default = "default_path")]
+    path: PathBuf,
+}
+
+impl Default for SinkConfig {
+    fn default() -> Self {
+        Self {
+            path: default_path(),
+        }
+    }
+}
+
+fn default_path() -> PathBuf {
+    STDOUT_PATH.into()
+}
+
+/// Writes the reports as JSON to a file or to stdout if the path is `-`.
+pub struct Sink {
+    config: SinkConfig,
+}
+
+impl Sink {
+    pub fn new(config: SinkConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl Reporter for Sink {
+    fn push_report(&self, reports: &[TestReport], failed: bool) -> Result<()> {
+        let json = serde_json::to_string_pretty(&Payload { failed, reports })
+            .context("failed to serialize report")?;
+        if self.config.path == Path::new(STDOUT_PATH) {
+            println!("{json}");
+            Ok(())
+        } else {
+            fs::write(&self.config.path, json + "\n")
+                .with_context(|| format!("failed to write report to {:?}", self.config.path))
+        }
+    }
+
+    fn writes_to_stdout(&self) -> bool {
+        self.config.path == Path::new(STDOUT_PATH)
+    }
+}
@@ -0,0 +1,193 @@
+use super::{Payload, Reporter};
+use crate::testing::result::TestReport;
+use anyhow::{bail, Context, Result};
+use reqwest::{
+    blocking::Client,
+    header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE},
+};
+use serde::Deserialize;
+use std::{collections::BTreeMap, env, thread, time::Duration};
+
+const WEBHOOK_RETRIES: usize = 3;
+const WEBHOOK_RETRY_DELAY: Duration = Duration::from_secs(1);
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct WebhookConfig {
+    url: String,
+    /// Values like `$NAME` are taken from the environment, so the secrets stay out of the file.
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    #[serde(default = "default_retries")]
+    retries: usize,
+    #[serde(default = "default_timeout", with = "humantime_serde")]
+    timeout: Duration,
+}
+
+fn default_retries() -> usize {
+    WEBHOOK_RETRIES
+}
+
+fn default_timeout() -> Duration {
+    WEBHOOK_TIMEOUT
+}
+
+/// Posts the reports as JSON to an arbitrary grading server.
+pub struct Webhook {
+    url: String,
+    retries: usize,
+    client: Client,
+}
+
+impl Webhook {
+    pub fn new(config: WebhookConfig) -> Result<Self> {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        for (name, value) in &config.headers {
+            let value = match value.strip_prefix('$') {
+                Some(var) => env::var(var)
+                    .with_context(|| format!("no {var} variable for header \"{name}\""))?,
+                None => value.clone(),
+            };
+            headers.insert(
+                HeaderName::from_bytes(name.as_bytes())
+                    .with_context(|| format!("invalid header name \"{name}\""))?,
+                HeaderValue::from_str(&value)
+                    .with_context(|| format!("invalid value of header \"{name}\""))?,
+            );
+        }
+        let client = Client::builder()
+            .default_headers(headers)
+            .timeout(config.timeout)
+            .build()
+            .context("failed to create http client")?;
+        Ok(Self {
+            url: config.url,
+            retries: config.retries,
+            client,
+        })
+    }
+}
+
+impl Reporter for Webhook {
+    fn push_report(&self, reports: &[TestReport], failed: bool) -> Result<()> {
+        let body = serde_json::to_vec(&Payload { failed, reports })
+            .context("failed to serialize report")?;
+        let mut last_error = None;
+        for attempt in 0..self.retries {
+            if attempt > 0 {
+                thread::sleep(WEBHOOK_RETRY_DELAY);
+            }
+            match self.client.post(&self.url).body(body.clone()).send() {
+                Ok(response) if response.status().is_success() => return Ok(()),
+                // The server has understood the report and rejected it, no reason to retry.
+                Ok(response) if response.status().is_client_error() => {
+                    bail!("webhook rejected the report: {}", response.status())
+                }
+                Ok(response) => last_error = Some(format!("{}", response.status())),
+                Err(err) => last_error = Some(format!("{err:#}")),
+            }
+        }
+        bail!(
+            "{} posts to webhook failed, the last one with: {}",
+            self.retries,
+            last_error.unwrap_or_default()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        thread::JoinHandle,
+    };
+
+    /// The head of a request in lowercase and its body.
+    type Request = (String, Vec<u8>);
+
+    /// A stand-in grading server answering the requests with `statuses` in order.
+    /// Returns the URL and the requests it got.
+    fn serve(statuses: &[u16]) -> (String, JoinHandle<Vec<Request>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/report", listener.local_addr().unwrap());
+        let statuses = statuses.to_vec();
+        let server = thread::spawn(move || {
+            let mut requests = Vec::new();
+            for status in statuses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(&stream);
+                let mut head = String::new();
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim_end().is_empty() {
+                        break;
+                    }
+                    head += &line.to_lowercase();
+                }
+                let len = head
+                    .lines()
+                    .find_map(|line| line.strip_prefix("content-length:"))
+                    .map_or(0, |len| len.trim().parse().unwrap());
+                let mut body = vec![0; len];
+                reader.read_exact(&mut body).unwrap();
+                write!(
+                    &stream,
+                    "HTTP/1.1 {status} Status\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                )
+                .unwrap();
+                requests.push((head, body));
+            }
+            requests
+        });
+        (url, server)
+    }
+
+    fn webhook(url: &str) -> Webhook {
+        let config = format!("url: {url}\nheaders:\n  X-Token: secret\nretries: 2\n");
+        Webhook::new(serde_yaml::from_str(&config).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn posts_report() {
+        let (url, server) = serve(&[500, 200]);
+        let reports = [TestReport::new("group/problem".to_string())];
+        webhook(&url).push_report(&reports, false).unwrap();
+
+        let requests = server.join().unwrap();
+        assert_eq!(requests.len(), 2);
+        let (head, body) = &requests[1];
+        assert!(head.starts_with("post /report "));
+        assert!(head.contains("content-type: application/json"));
+        assert!(head.contains("x-token: secret"));
+        let body: serde_json::Value = serde_json::from_slice(body).unwrap();
+        assert_eq!(body["failed"], false);
+        assert_eq!(body["reports"][0]["problem"], "group/problem");
+    }
+
+    #[test]
+    fn rejected_report() {
+        let (url, server) = serve(&[403]);
+        let err = webhook(&url).push_report(&[], true).unwrap_err();
+        assert_eq!(
+            format!("{err:#}"),
+            "webhook rejected the report: 403 Forbidden"
+        );
+        assert_eq!(server.join().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn server_errors() {
+        let (url, server) = serve(&[500, 502]);
+        let err = webhook(&url).push_report(&[], true).unwrap_err();
+        assert_eq!(
+            format!("{err:#}"),
+            "2 posts to webhook failed, the last one with: 502 Bad Gateway"
+        );
+        assert_eq!(server.join().unwrap().len(), 2);
+    }
+}
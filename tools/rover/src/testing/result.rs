@@ -0,0 +1,359 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize, Serializer};
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+const OUTPUT_TAIL_LINES: usize = 40;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Outcome {
+    Passed,
+    Failed,
+    TimedOut,
+    Skipped,
+}
+
+impl Outcome {
+    pub fn is_failure(self) -> bool {
+        matches!(self, Self::Failed | Self::TimedOut)
+    }
+
+    fn merge(self, other: Self) -> Self {
+        match (self, other) {
+            (Self::TimedOut, _) | (_, Self::TimedOut) => Self::TimedOut,
+            (Self::Failed, _) | (_, Self::Failed) => Self::Failed,
+            (Self::Skipped, Self::Skipped) => Self::Skipped,
+            _ => Self::Passed,
+        }
+    }
+}
+
+impl std::fmt::Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Passed => write!(f, "passed"),
+            Self::Failed => write!(f, "failed"),
+            Self::TimedOut => write!(f, "timed out"),
+            Self::Skipped => write!(f, "skipped"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Violation {
+    file: PathBuf,
+    line: Option<usize>,
+    message: String,
+}
+
+impl Violation {
+    pub fn new(file: &Path, line: Option<usize>, message: String) -> Self {
+        Self {
+            file: file.to_path_buf(),
+            line,
+            message,
+        }
+    }
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{line}: {}", self.file.display(), self.message),
+            None => write!(f, "{}: {}", self.file.display(), self.message),
+        }
+    }
+}
+
+/// A single test run by a `cargo test` command.
+#[derive(Clone, Debug, Serialize)]
+pub struct TestCase {
+    binary: String,
+    name: String,
+    outcome: Outcome,
+}
+
+impl TestCase {
+    pub fn new(binary: String, name: String, outcome: Outcome) -> Self {
+        Self {
+            binary,
+            name,
+            outcome,
+        }
+    }
+
+    pub fn binary(&self) -> &str {
+        &self.binary
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn outcome(&self) -> Outcome {
+        self.outcome
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CommandResult {
+    name: String,
+    outcome: Outcome,
+    #[serde(serialize_with = "serialize_secs")]
+    duration: Duration,
+    exit_code: Option<i32>,
+    message: Option<String>,
+    stdout_tail: String,
+    stderr_tail: String,
+    violations: Vec<Violation>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tests: Vec<TestCase>,
+}
+
+impl CommandResult {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            outcome: Outcome::Passed,
+            duration: Duration::ZERO,
+            exit_code: None,
+            message: None,
+            stdout_tail: String::new(),
+            stderr_tail: String::new(),
+            violations: Vec::new(),
+            tests: Vec::new(),
+        }
+    }
+
+    pub fn skipped(name: String) -> Self {
+        Self {
+            outcome: Outcome::Skipped,
+            ..Self::new(name)
+        }
+    }
+
+    pub fn fail(&mut self, message: String) {
+        self.outcome = Outcome::Failed;
+        self.message = Some(message);
+    }
+
+    pub fn time_out(&mut self, message: String) {
+        self.outcome = Outcome::TimedOut;
+        self.message = Some(message);
+    }
+
+    pub fn add_violation(&mut self, violation: Violation) {
+        self.outcome = Outcome::Failed;
+        self.violations.push(violation);
+    }
+
+    pub fn set_duration(&mut self, duration: Duration) {
+        self.duration = duration;
+    }
+
+    pub fn set_exit_code(&mut self, exit_code: Option<i32>) {
+        self.exit_code = exit_code;
+    }
+
+    pub fn set_output(&mut self, stdout: &[u8], stderr: &[u8]) {
+        self.stdout_tail = tail(stdout);
+        self.stderr_tail = tail(stderr);
+    }
+
+    pub fn set_tests(&mut self, tests: Vec<TestCase>) {
+        self.tests = tests;
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn outcome(&self) -> Outcome {
+        self.outcome
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    pub fn tests(&self) -> &[TestCase] {
+        self.tests.as_slice()
+    }
+
+    pub fn stdout_tail(&self) -> &str {
+        &self.stdout_tail
+    }
+
+    pub fn stderr_tail(&self) -> &str {
+        &self.stderr_tail
+    }
+
+    pub fn violations(&self) -> &[Violation] {
+        self.violations.as_slice()
+    }
+
+    /// Short description of the failure: the message or the first violation.
+    pub fn failure_summary(&self) -> String {
+        match (&self.message, self.violations.first()) {
+            (Some(message), _) => message.clone(),
+            (None, Some(violation)) => violation.to_string(),
+            (None, None) => "failed".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct StepResult {
+    name: String,
+    outcome: Outcome,
+    #[serde(serialize_with = "serialize_secs")]
+    duration: Duration,
+    commands: Vec<CommandResult>,
+}
+
+impl StepResult {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            outcome: Outcome::Passed,
+            duration: Duration::ZERO,
+            commands: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, command: CommandResult) {
+        self.outcome = if self.commands.is_empty() {
+            command.outcome()
+        } else {
+            self.outcome.merge(command.outcome())
+        };
+        self.duration += command.duration();
+        self.commands.push(command);
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn outcome(&self) -> Outcome {
+        self.outcome
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    pub fn commands(&self) -> &[CommandResult] {
+        self.commands.as_slice()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TestReport {
+    problem: String,
+    outcome: Outcome,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    score: Option<f64>,
+    steps: Vec<StepResult>,
+}
+
+impl TestReport {
+    pub fn new(problem: String) -> Self {
+        Self {
+            problem,
+            outcome: Outcome::Passed,
+            score: None,
+            steps: Vec::new(),
+        }
+    }
+
+    /// A report of a problem that could not be launched at all, e.g. because of its config.
+    pub fn failed_to_launch(problem: String, err: &anyhow::Error) -> Self {
+        let mut command = CommandResult::new("launch".to_string());
+        command.fail(format!("{err:#}"));
+        let mut step = StepResult::new("setup".to_string());
+        step.push(command);
+        let mut report = Self::new(problem);
+        report.push(step);
+        report
+    }
+
+    pub fn push(&mut self, step: StepResult) {
+        if step.outcome().is_failure() {
+            self.outcome = Outcome::Failed;
+        }
+        self.steps.push(step);
+    }
+
+    pub fn set_score(&mut self, score: f64) {
+        self.score = Some(score);
+    }
+
+    pub fn problem(&self) -> &str {
+        &self.problem
+    }
+
+    /// Share of the weighted tests passed, if the problem gives partial credit.
+    pub fn score(&self) -> Option<f64> {
+        self.score
+    }
+
+    pub fn outcome(&self) -> Outcome {
+        self.outcome
+    }
+
+    pub fn steps(&self) -> &[StepResult] {
+        self.steps.as_slice()
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.steps.iter().map(StepResult::duration).sum()
+    }
+
+    pub fn commands(&self) -> impl Iterator<Item = (&StepResult, &CommandResult)> {
+        self.steps
+            .iter()
+            .flat_map(|step| step.commands().iter().map(move |command| (step, command)))
+    }
+
+    pub fn count(&self, outcome: Outcome) -> usize {
+        self.commands()
+            .filter(|(_, command)| command.outcome() == outcome)
+            .count()
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = (&StepResult, &CommandResult)> {
+        self.commands()
+            .filter(|(_, command)| command.outcome().is_failure())
+    }
+
+    pub fn ensure_passed(&self) -> Result<()> {
+        let mut failed = self.failures();
+        if let Some((step, command)) = failed.next() {
+            let more = match failed.count() {
+                0 => String::new(),
+                count => format!(" (and {count} more failed commands)"),
+            };
+            bail!(
+                "step \"{}\", command \"{}\" failed: {}{more}",
+                step.name(),
+                command.name(),
+                command.failure_summary()
+            )
+        }
+        Ok(())
+    }
+}
+
+fn tail(output: &[u8]) -> String {
+    let output = String::from_utf8_lossy(output);
+    let lines: Vec<_> = output.lines().collect();
+    lines[lines.len().saturating_sub(OUTPUT_TAIL_LINES)..].join("\n")
+}
+
+fn serialize_secs<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64())
+}
@@ -0,0 +1,144 @@
+use super::result::{Outcome, TestReport};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// Partial credit for the tests of a problem, the `scoring` section of `.config.yml`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Scoring {
+    #[serde(default = "default_weight")]
+    default_weight: f64,
+    /// Weights of test names like `tests::stress` or of test binaries like `tests/tests.rs`.
+    #[serde(default)]
+    weights: BTreeMap<String, f64>,
+}
+
+fn default_weight() -> f64 {
+    1.0
+}
+
+impl Scoring {
+    /// The weighted share of passed tests from 0 to 1. Any failed command besides the
+    /// tests, e.g. a linter or a build error, gives no credit at all.
+    pub fn score(&self, report: &TestReport) -> f64 {
+        // The weight of a binary is split between its tests without weights of their own.
+        let mut binary_tests = BTreeMap::<&str, usize>::new();
+        for (_, command) in report.commands() {
+            for test in command.tests() {
+                if test.outcome() != Outcome::Skipped
+                    && !self.weights.contains_key(test.name())
+                    && self.weights.contains_key(test.binary())
+                {
+                    *binary_tests.entry(test.binary()).or_default() += 1;
+                }
+            }
+        }
+
+        let mut earned = 0.0;
+        let mut total = 0.0;
+        let mut matched = Vec::new();
+        for (_, command) in report.commands() {
+            if command.tests().is_empty() {
+                if command.outcome().is_failure() {
+                    return 0.0;
+                }
+                continue;
+            }
+            for test in command.tests() {
+                if test.outcome() == Outcome::Skipped {
+                    continue;
+                }
+                let (key, weight) = match (
+                    self.weights.get_key_value(test.name()),
+                    self.weights.get_key_value(test.binary()),
+                ) {
+                    (Some((key, weight)), _) => (Some(key), *weight),
+                    (None, Some((key, weight))) => {
+                        (Some(key), weight / binary_tests[key.as_str()] as f64)
+                    }
+                    (None, None) => (None, self.default_weight),
+                };
+                matched.extend(key);
+                total += weight;
+                if test.outcome() == Outcome::Passed {
+                    earned += weight;
+                }
+            }
+        }
+        // A weighted test that has not run at all counts as failed.
+        total += self
+            .weights
+            .iter()
+            .filter(|(key, _)| !matched.contains(key))
+            .map(|(_, weight)| weight)
+            .sum::<f64>();
+        if total > 0.0 {
+            earned / total
+        } else if report.outcome().is_failure() {
+            0.0
+        } else {
+            1.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::result::{CommandResult, StepResult, TestCase};
+
+    fn report(tests: &[(&str, &str, Outcome)]) -> TestReport {
+        let mut command = CommandResult::new("cargo-test".to_string());
+        command.set_tests(
+            tests
+                .iter()
+                .map(|(binary, name, outcome)| {
+                    TestCase::new(binary.to_string(), name.to_string(), *outcome)
+                })
+                .collect(),
+        );
+        let mut step = StepResult::new("testing".to_string());
+        step.push(command);
+        let mut report = TestReport::new("group/problem".to_string());
+        report.push(step);
+        report
+    }
+
+    fn scoring(yml: &str) -> Scoring {
+        serde_yaml::from_str(yml).unwrap()
+    }
+
+    #[test]
+    fn binary_weight_is_split() {
+        let scoring = scoring("weights:\n  tests/stress.rs: 3\n  tests::named: 2\n");
+        let report = report(&[
+            ("tests/stress.rs", "a", Outcome::Passed),
+            ("tests/stress.rs", "b", Outcome::Failed),
+            ("tests/stress.rs", "c", Outcome::Passed),
+            ("tests/stress.rs", "d", Outcome::Skipped),
+            ("tests/stress.rs", "tests::named", Outcome::Passed),
+            ("tests/tests.rs", "e", Outcome::Failed),
+        ]);
+        // 2 of 3 for the binary, 2 for the named test, 0 of 1 for the default one.
+        assert!((scoring.score(&report) - 4.0 / 6.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn weighted_test_not_run() {
+        let scoring = scoring("default-weight: 2\nweights:\n  tests::missing: 4\n");
+        let report = report(&[("src/lib.rs", "tests::unit", Outcome::Passed)]);
+        assert!((scoring.score(&report) - 2.0 / 6.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn failed_command() {
+        let scoring = scoring("{}");
+        let mut report = report(&[("src/lib.rs", "tests::unit", Outcome::Passed)]);
+        let mut step = StepResult::new("linters".to_string());
+        let mut command = CommandResult::new("cargo-clippy".to_string());
+        command.fail("warnings".to_string());
+        step.push(command);
+        report.push(step);
+        assert_eq!(scoring.score(&report), 0.0);
+    }
+}
@@ -1,6 +1,141 @@
-use crate::repository::problem::Problem;
-use anyhow::Result;
+use super::{history::LastRun, output::OutputFormat, report::Reporter, result::TestReport};
+use crate::repository::{
+    context::{Echo, LaunchOptions},
+    problem::Problem,
+    repo::Repository,
+    sandbox::Sandbox,
+};
+use anyhow::{bail, Result};
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+};
 
-pub fn test_problem(problem: Problem) -> Result<()> {
-    problem.launch_all_steps()
+/// Every problem tested with `--all` gets its own cargo target directory, so the jobs
+/// don't wait for each other on the build directory lock.
+const JOBS_TARGET_FOLDER: &str = "target/rover/jobs";
+const TARGET_FOLDER: &str = "target";
+
+pub fn test_problem(
+    problem: Problem,
+    output_format: OutputFormat,
+    keep_going: bool,
+    sandbox: Option<Sandbox>,
+) -> Result<TestReport> {
+    let options = launch_options(&problem, output_format.echo(), keep_going, sandbox);
+    let report = problem.launch_all_steps(&options)?;
+    output_format.print(&report)?;
+    LastRun::save(&problem, &report)?;
+    Ok(report)
+}
+
+/// Options to test a single problem with, shared by `rover test` and `rover watch`.
+pub(super) fn launch_options(
+    problem: &Problem,
+    echo: Echo,
+    keep_going: bool,
+    sandbox: Option<Sandbox>,
+) -> LaunchOptions {
+    let mut options = LaunchOptions::new(echo).keep_going(keep_going);
+    // The sandbox has to know which directory the build may write to.
+    if sandbox.is_some() {
+        options = options.target_dir(problem.repository_path().join(TARGET_FOLDER));
+    }
+    options.sandbox(sandbox)
+}
+
+/// Tests the problems of the repository, at most `jobs` at once. With `group` set only
+/// the problems of the group are tested.
+pub fn test_all_problems(
+    repository: &Repository,
+    group: Option<&str>,
+    jobs: usize,
+    output_format: OutputFormat,
+    keep_going: bool,
+    sandbox: Option<Sandbox>,
+) -> Result<Vec<TestReport>> {
+    let problems: Vec<_> = repository
+        .problems()?
+        .into_iter()
+        .filter(|problem| group.is_none_or(|group| problem.group() == group))
+        .collect();
+    if problems.is_empty() {
+        bail!("no problems to test")
+    }
+    if jobs == 0 {
+        bail!("the number of jobs must be positive")
+    }
+    let next = AtomicUsize::new(0);
+    let reports = Mutex::new(Vec::new());
+    thread::scope(|scope| {
+        for _ in 0..jobs.min(problems.len()) {
+            scope.spawn(|| {
+                while let Some(problem) = problems.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    let report = test_in_isolation(problem, keep_going, sandbox.clone());
+                    reports.lock().unwrap().push(report);
+                }
+            });
+        }
+    });
+    let mut reports = reports.into_inner().unwrap();
+    if reports.len() != problems.len() {
+        bail!(
+            "tested {} problems out of {}",
+            reports.len(),
+            problems.len()
+        )
+    }
+    reports.sort_by(|a: &TestReport, b| a.problem().cmp(b.problem()));
+    output_format.print_all(&reports)?;
+    Ok(reports)
+}
+
+/// Pushes the results to the reporter and fails if testing has failed.
+pub fn report_results(reporter: &dyn Reporter, reports: Result<Vec<TestReport>>) -> Result<()> {
+    let (reports, testing_result) = match reports {
+        Ok(reports) => {
+            let testing_result = ensure_passed(&reports);
+            (reports, testing_result)
+        }
+        Err(err) => (Vec::new(), Err(err)),
+    };
+    let report_push = reporter.push_report(&reports, testing_result.is_err());
+    testing_result.and(report_push)
+}
+
+fn ensure_passed(reports: &[TestReport]) -> Result<()> {
+    if let [report] = reports {
+        return report.ensure_passed();
+    }
+    let failed = reports
+        .iter()
+        .filter(|report| report.outcome().is_failure())
+        .count();
+    if failed > 0 {
+        bail!("{failed} of {} problems failed", reports.len())
+    }
+    Ok(())
+}
+
+fn test_in_isolation(problem: &Problem, keep_going: bool, sandbox: Option<Sandbox>) -> TestReport {
+    let target_dir = problem
+        .repository_path()
+        .join(JOBS_TARGET_FOLDER)
+        .join(problem.group())
+        .join(problem.title());
+    let options = LaunchOptions::new(Echo::Quiet)
+        .keep_going(keep_going)
+        .target_dir(target_dir)
+        .sandbox(sandbox);
+    let report = problem
+        .launch_all_steps(&options)
+        .unwrap_or_else(|err| TestReport::failed_to_launch(problem.branch_name(), &err));
+    eprintln!("{}: {}", problem.branch_name(), report.outcome());
+    if let Err(err) = LastRun::save(problem, &report) {
+        eprintln!("{}: {err:#}", problem.branch_name());
+    }
+    report
 }
@@ -0,0 +1,114 @@
+use super::{history::LastRun, result::TestReport, test::launch_options};
+use crate::repository::{
+    context::{Echo, LaunchOptions},
+    problem::Problem,
+    sandbox::Sandbox,
+};
+use anyhow::{bail, Context, Result};
+use notify::{event::ModifyKind, Event, EventKind, RecursiveMode, Watcher};
+use std::{
+    collections::BTreeSet,
+    path::PathBuf,
+    sync::mpsc::{self, RecvTimeoutError},
+    time::Duration,
+};
+
+/// Saving a file often touches it several times, e.g. an editor truncates the file
+/// and writes it in parts.
+const DEBOUNCE_TIMEOUT: Duration = Duration::from_millis(300);
+
+/// Tests the problem on every change of its user files until rover is interrupted.
+pub fn watch_problem(problem: Problem, keep_going: bool, sandbox: Option<Sandbox>) -> Result<()> {
+    let config = problem.config()?;
+    let files = config.get_absolute_user_files();
+    if files.is_empty() {
+        bail!("no user files to watch, check \"allowed-patterns\" of the config")
+    }
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher =
+        notify::recommended_watcher(sender).context("failed to start watching the files")?;
+    // Editors replace files instead of writing them in place, so the directories
+    // are watched.
+    let dirs: BTreeSet<_> = files
+        .iter()
+        .filter_map(|file| file.parent().map(PathBuf::from))
+        .collect();
+    for dir in &dirs {
+        watcher
+            .watch(dir, RecursiveMode::NonRecursive)
+            .with_context(|| format!("failed to watch {dir:?}"))?;
+    }
+    let options = launch_options(&problem, Echo::Quiet, keep_going, sandbox);
+
+    test_once(&problem, &options)?;
+    loop {
+        // Waits for the first change and then until the files are quiet for a while.
+        let mut changed = BTreeSet::new();
+        loop {
+            let event = if changed.is_empty() {
+                receiver.recv().ok()
+            } else {
+                match receiver.recv_timeout(DEBOUNCE_TIMEOUT) {
+                    Ok(event) => Some(event),
+                    Err(RecvTimeoutError::Timeout) => break,
+                    Err(RecvTimeoutError::Disconnected) => None,
+                }
+            };
+            let event = event
+                .context("watcher has stopped")?
+                .context("failed to watch the files")?;
+            if !is_change(&event) {
+                continue;
+            }
+            changed.extend(
+                files
+                    .iter()
+                    .zip(config.get_relative_user_files())
+                    .filter(|(file, _)| event.paths.iter().any(|path| path == *file))
+                    .map(|(_, relative)| relative.as_path()),
+            );
+        }
+        eprintln!();
+        for file in changed {
+            eprintln!("{} changed", file.display());
+        }
+        test_once(&problem, &options)?;
+    }
+}
+
+/// Reading the files, e.g. by the linters, is not a change.
+fn is_change(event: &Event) -> bool {
+    match event.kind {
+        EventKind::Create(_) | EventKind::Remove(_) => true,
+        EventKind::Modify(kind) => !matches!(kind, ModifyKind::Metadata(_)),
+        EventKind::Access(_) | EventKind::Any | EventKind::Other => false,
+    }
+}
+
+fn test_once(problem: &Problem, options: &LaunchOptions) -> Result<()> {
+    let report = problem
+        .launch_all_steps(options)
+        .unwrap_or_else(|err| TestReport::failed_to_launch(problem.branch_name(), &err));
+    LastRun::save(problem, &report)?;
+    match report.failures().next() {
+        None => eprintln!("passed in {:.2?}, waiting for changes", report.duration()),
+        Some((step, command)) => {
+            eprintln!(
+                "\n{}/{}: {}",
+                step.name(),
+                command.name(),
+                command.failure_summary()
+            );
+            for violation in command.violations() {
+                eprintln!("  {violation}");
+            }
+            for output in [command.stdout_tail(), command.stderr_tail()] {
+                if !output.trim().is_empty() {
+                    eprintln!("{}", output.trim_end());
+                }
+            }
+            eprintln!("failed, waiting for changes");
+        }
+    }
+    Ok(())
+}
@@ -1 +1,3 @@
+pub(crate) mod process_group;
+pub(crate) mod table;
 pub(crate) mod util_macro;
@@ -0,0 +1,56 @@
+use crate::repository::overlay::restore_active_overlays;
+use anyhow::{Context, Result};
+use nix::{
+    errno::Errno,
+    sys::signal::{killpg, Signal},
+    unistd::Pid,
+};
+use std::{
+    collections::HashSet,
+    os::unix::process::CommandExt,
+    process::{self, Child},
+    sync::Mutex,
+};
+
+/// Process groups of the commands that are running now, killed on Ctrl+C.
+static RUNNING_GROUPS: Mutex<Option<HashSet<i32>>> = Mutex::new(None);
+
+/// Kills the running commands when rover is interrupted: they live in their own
+/// process groups and won't get the signal from the terminal. The private tests are
+/// removed, as the drops won't run on exit.
+pub fn install_interrupt_handler() -> Result<()> {
+    ctrlc::set_handler(|| {
+        if let Some(groups) = RUNNING_GROUPS.lock().unwrap().as_ref() {
+            for group in groups {
+                let _ = killpg(Pid::from_raw(*group), Signal::SIGKILL);
+            }
+        }
+        restore_active_overlays();
+        process::exit(130);
+    })
+    .context("failed to set Ctrl+C handler")
+}
+
+/// Spawns the command as a leader of a new process group, so the command can be killed
+/// together with everything it has launched.
+pub fn spawn(cmd: &mut process::Command) -> std::io::Result<Child> {
+    let child = cmd.process_group(0).spawn()?;
+    RUNNING_GROUPS
+        .lock()
+        .unwrap()
+        .get_or_insert_with(HashSet::new)
+        .insert(child.id() as i32);
+    Ok(child)
+}
+
+/// Kills the whole process group of the command. The group may be already gone.
+pub fn kill(child: &Child) -> Result<()> {
+    let group = child.id() as i32;
+    if let Some(groups) = RUNNING_GROUPS.lock().unwrap().as_mut() {
+        groups.remove(&group);
+    }
+    match killpg(Pid::from_raw(group), Signal::SIGKILL) {
+        Ok(()) | Err(Errno::ESRCH) => Ok(()),
+        Err(err) => Err(err).context("failed to kill process group"),
+    }
+}